@@ -0,0 +1,75 @@
+//! What happens when the comparator is wrong, and a checked sort that looks
+//! for the signs of it after each merge.
+//!
+//! None of the sorts trust the comparator with memory safety. Every index and
+//! count that comes out of a comparison, whether it is a gallop, a binary
+//! search or a single step of a merge, is bounded by the length of the slice
+//! it was found in, and the merges only ever move elements between the list
+//! and the scratch space as a whole, putting back whatever is left when they
+//! stop early. So a comparator that isn't transitive, or says `a < b` and
+//! `b < a` at the same time, or changes its mind halfway through, can leave
+//! the list in any order at all, but every element is still in it exactly
+//! once. The same goes for a comparator that panics or returns an error.
+//!
+//! The sorts also always finish. Every merge either moves elements without
+//! asking the comparator anything more, or splits into merges that are
+//! strictly smaller, however the comparator answers, and that holds for the
+//! in-place merges used when there's no scratch space too.
+
+#[cfg(test)]
+mod tests;
+
+use core::fmt;
+use core::ops::Range;
+
+/// Proof that the comparator isn't a total order, found by
+/// `sort_by_checked`.
+///
+/// Whenever two sorted runs have been merged, the checked sort makes sure the
+/// result is sorted too, comparing each element with the one after it. If the
+/// two runs really were sorted, and the comparator is consistent, that can't
+/// fail, whether or not the merge galloped. Only what the merge leaves behind
+/// is checked, not the comparisons made during it, and only neighbours are
+/// compared, so a comparator can be wrong in ways the check can't see: if it
+/// says `a < b < c < a`, the list `[a, b, c]` passes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComparatorViolation {
+    /// Where the first of the two elements is in the list.
+    pub first: usize,
+    /// Where the second element is, which is always `first + 1`. The
+    /// comparator says it goes before the first one.
+    pub second: usize,
+    /// The part of the list the two elements were just merged into, or the
+    /// whole list if it was short enough to be sorted by insertion alone.
+    pub merged: Range<usize>,
+}
+
+impl fmt::Display for ComparatorViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the comparator put the element at {} before the one at {}, after sorting {}..{}", self.second, self.first, self.merged.start, self.merged.end)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ComparatorViolation {}
+
+/// The error returned by `try_sort_by_checked`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckError<E> {
+    /// The comparator returned an error.
+    Comparator(E),
+    /// The comparator contradicted itself.
+    Violation(ComparatorViolation),
+}
+
+impl<E: fmt::Display> fmt::Display for CheckError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CheckError::Comparator(ref e) => e.fmt(f),
+            CheckError::Violation(ref v) => v.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug + fmt::Display> std::error::Error for CheckError<E> {}
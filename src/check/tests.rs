@@ -0,0 +1,151 @@
+//! What happens when the comparator is wrong, and a checked sort that catches
+//! it.
+
+use std::boxed::Box;
+use std::vec::Vec;
+use core::cmp::Ordering;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use check::{CheckError, ComparatorViolation};
+use policy::{Timsort, Powersort, ShiversSort, Peeksort, MergePolicy};
+use sort::{sort_checked, try_sort_checked, try_sort_in, sort_with_buffer, RunStack};
+use sorter::TimSorter;
+use test_util::{pseudo_random, tagged};
+
+/// A comparator that answers at random, so it contradicts itself all the
+/// time. It only needs `&self`, so the parallel sort can use it too.
+struct Random(AtomicUsize);
+
+impl Random {
+    fn new(seed: usize) -> Random {
+        Random(AtomicUsize::new(seed * 2 + 1))
+    }
+
+    fn cmp<T>(&self, _: &T, _: &T) -> Ordering {
+        // xorshift. Other threads might get the same answer, which is fine.
+        let mut x = self.0.load(AtomicOrdering::Relaxed);
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0.store(x, AtomicOrdering::Relaxed);
+        match x % 3 {
+            0 => Ordering::Less,
+            1 => Ordering::Equal,
+            _ => Ordering::Greater,
+        }
+    }
+}
+
+/// An element on the heap, so that anything duplicated would be freed twice.
+struct Owned(Box<usize>);
+
+fn boxes(len: usize) -> Vec<Owned> {
+    (0..len).map(|i| Owned(Box::new(i))).collect()
+}
+
+/// Checks that `list` still holds each of `0..len` exactly once.
+fn check_permutation(list: &[Owned]) {
+    let mut seen = vec![false; list.len()];
+    for x in list {
+        assert!(!seen[*x.0]);
+        seen[*x.0] = true;
+    }
+}
+
+fn check_policy<P: MergePolicy>(mut policy: P, len: usize, seed: usize) {
+    let random = Random::new(seed);
+    let mut list = boxes(len);
+    try_sort_in(&mut list, |a, b| Ok::<Ordering, ()>(random.cmp(a, b)), &mut policy, &mut RunStack::new(), &mut Vec::new(), &mut (), &mut ()).unwrap();
+    check_permutation(&list);
+}
+
+/// Test that a comparator that answers at random never loses or duplicates
+/// anything, whichever way the list is sorted.
+#[test]
+fn random_comparator() {
+    for &len in &[0, 1, 2, 10, 63, 64, 65, 200, 1000, 5000] {
+        for seed in 0..10 {
+            check_policy(Timsort, len, seed);
+            check_policy(Powersort, len, seed);
+            check_policy(ShiversSort, len, seed);
+            check_policy(Peeksort, len, seed);
+            let random = Random::new(seed);
+            let mut list = boxes(len);
+            let mut buf: Vec<MaybeUninit<Owned>> = (0..len / 10).map(|_| MaybeUninit::uninit()).collect();
+            sort_with_buffer(&mut list, &mut buf, |a, b| random.cmp(a, b));
+            check_permutation(&list);
+            let result = sort_checked(&mut list, |a, b| random.cmp(a, b));
+            assert!(len < 10 || result.is_err());
+            check_permutation(&list);
+            // With no scratch space at all, every merge is done in place.
+            let mut list = boxes(len);
+            TimSorter::with_max_scratch(0).sort_by(&mut list, |a, b| random.cmp(a, b));
+            check_permutation(&list);
+        }
+    }
+}
+
+/// Test the same thing for the parallel sort, where the merges are split up
+/// between threads.
+#[cfg(feature = "std")]
+#[test]
+fn random_comparator_par() {
+    use par::par_sort_with_threads;
+    for seed in 0..5 {
+        let random = Random::new(seed);
+        let mut list = boxes(50_000);
+        par_sort_with_threads(&mut list, 4, |a, b| random.cmp(a, b));
+        check_permutation(&list);
+    }
+}
+
+/// Says multiples of seven go before everything, including each other, so
+/// there's no right order.
+fn contrary(a: &usize, b: &usize) -> Ordering {
+    if a % 7 == 0 || b % 7 == 0 {
+        Ordering::Less
+    } else {
+        a.cmp(b)
+    }
+}
+
+/// Test that the checked sort catches a comparator that isn't antisymmetric,
+/// and names two elements that really are out of order.
+#[test]
+fn catches_violations() {
+    for &len in &[10, 63, 64, 1000] {
+        let original = pseudo_random(len, 1009, 0);
+        let mut list = original.clone();
+        let v = sort_checked(&mut list, contrary).unwrap_err();
+        assert_eq!(v.second, v.first + 1);
+        assert!(v.merged.start <= v.first && v.second < v.merged.end);
+        assert_eq!(contrary(&list[v.second], &list[v.first]), Ordering::Less);
+        // Nothing was lost on the way.
+        let (mut list, mut original) = (list, original);
+        list.sort();
+        original.sort();
+        assert_eq!(list, original);
+    }
+}
+
+/// Test that the checked sort sorts just like the normal one when the
+/// comparator is fine, and passes on the comparator's own errors.
+#[test]
+fn consistent() {
+    for &len in &[0, 1, 63, 64, 1000, 5000] {
+        let mut list = tagged(pseudo_random(len, 101, 0));
+        assert_eq!(sort_checked(&mut list, |a, b| a.0.cmp(&b.0)), Ok(()));
+        for i in 1..list.len() {
+            assert!(list[i - 1] < list[i]);
+        }
+    }
+    let mut list: Vec<usize> = (0..1000).rev().collect();
+    let mut count = 0;
+    let result = try_sort_checked(&mut list, |a, b| {
+        count += 1;
+        if count == 500 { Err("stop") } else { Ok(a.cmp(b)) }
+    });
+    assert_eq!(result, Err(CheckError::Comparator("stop")));
+    let v = ComparatorViolation { first: 1, second: 2, merged: 0..3 };
+    assert_eq!(std::format!("{}", CheckError::<&str>::Violation(v)), "the comparator put the element at 2 before the one at 1, after sorting 0..3");
+}
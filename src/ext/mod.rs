@@ -0,0 +1,105 @@
+//! Sorting methods for slices, `Vec` and `VecDeque`, so they can be sorted
+//! with `list.timsort()` instead of `timsort::sort_by(&mut list[..], ...)`.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use key::{sort_by_key, sort_by_cached_key, permute_by_keys};
+use merge::merge_apart;
+use sort::sort;
+
+/// Adds methods that work like the standard library's `sort`, `sort_by`,
+/// `sort_by_key` and `sort_by_cached_key`, but use this crate's sort.
+///
+/// ```
+/// use timsort::TimSortExt;
+/// let mut list = vec![3, 1, 2];
+/// list.timsort();
+/// assert_eq!(list, vec![1, 2, 3]);
+/// ```
+pub trait TimSortExt<T> {
+    /// Sorts the elements. The sort is stable.
+    fn timsort(&mut self) where T: Ord;
+
+    /// Sorts the elements with a comparator, like `sort_by`.
+    fn timsort_by<F: FnMut(&T, &T) -> Ordering>(&mut self, f: F);
+
+    /// Sorts the elements by the key that `f` extracts, like `sort_by_key`.
+    fn timsort_by_key<K: Ord, F: FnMut(&T) -> K>(&mut self, f: F);
+
+    /// Sorts the elements by the key that `f` extracts, calling `f` once per
+    /// element, like `sort_by_cached_key`.
+    fn timsort_by_cached_key<K: Ord, F: FnMut(&T) -> K>(&mut self, f: F);
+}
+
+impl<T> TimSortExt<T> for [T] {
+    fn timsort(&mut self) where T: Ord {
+        sort(self, T::cmp)
+    }
+
+    fn timsort_by<F: FnMut(&T, &T) -> Ordering>(&mut self, f: F) {
+        sort(self, f)
+    }
+
+    fn timsort_by_key<K: Ord, F: FnMut(&T) -> K>(&mut self, f: F) {
+        sort_by_key(self, f)
+    }
+
+    fn timsort_by_cached_key<K: Ord, F: FnMut(&T) -> K>(&mut self, f: F) {
+        sort_by_cached_key(self, f)
+    }
+}
+
+impl<T> TimSortExt<T> for Vec<T> {
+    fn timsort(&mut self) where T: Ord {
+        self[..].timsort()
+    }
+
+    fn timsort_by<F: FnMut(&T, &T) -> Ordering>(&mut self, f: F) {
+        self[..].timsort_by(f)
+    }
+
+    fn timsort_by_key<K: Ord, F: FnMut(&T) -> K>(&mut self, f: F) {
+        self[..].timsort_by_key(f)
+    }
+
+    fn timsort_by_cached_key<K: Ord, F: FnMut(&T) -> K>(&mut self, f: F) {
+        self[..].timsort_by_cached_key(f)
+    }
+}
+
+/// The elements of a `VecDeque` can be split in two in memory, where the ring
+/// buffer wraps around. Each half is sorted on its own, and then the two are
+/// merged across the gap with `merge::merge_apart`, so nothing has to be moved
+/// just to make the deque contiguous.
+impl<T> TimSortExt<T> for VecDeque<T> {
+    fn timsort(&mut self) where T: Ord {
+        self.timsort_by(T::cmp)
+    }
+
+    fn timsort_by<F: FnMut(&T, &T) -> Ordering>(&mut self, mut f: F) {
+        let (first, second) = self.as_mut_slices();
+        sort(first, &mut f);
+        sort(second, &mut f);
+        match merge_apart(first, second, |a, b| Ok::<Ordering, Infallible>(f(a, b)), &mut Vec::new()) {
+            Ok(()) => (),
+            Err(e) => match e {},
+        }
+    }
+
+    fn timsort_by_key<K: Ord, F: FnMut(&T) -> K>(&mut self, mut f: F) {
+        self.timsort_by(|a, b| f(a).cmp(&f(b)))
+    }
+
+    fn timsort_by_cached_key<K: Ord, F: FnMut(&T) -> K>(&mut self, f: F) {
+        if self.len() < 2 {
+            return;
+        }
+        let indices: Vec<(K, usize)> = self.iter().map(f).enumerate().map(|(i, k)| (k, i)).collect();
+        permute_by_keys(indices, |i, j| self.swap(i, j));
+    }
+}
@@ -0,0 +1,68 @@
+//! Sorting methods for slices, `Vec` and `VecDeque`.
+
+use std::collections::VecDeque;
+use std::vec::Vec;
+use ext::TimSortExt;
+use test_util::{check_sorted, pseudo_random, tagged};
+
+type Deque = VecDeque<(usize, usize)>;
+
+fn pairs(len: usize) -> Vec<(usize, usize)> {
+    tagged(pseudo_random(len, 101, 1))
+}
+
+/// A deque whose elements wrap around the end of its buffer, with `front`
+/// of them in the second half in memory, which comes first.
+fn wrapped(len: usize, front: usize) -> Deque {
+    let list = pairs(len);
+    let mut deque = VecDeque::with_capacity(len);
+    for &x in list[front..].iter() {
+        deque.push_back(x);
+    }
+    for &x in list[..front].iter().rev() {
+        deque.push_front(x);
+    }
+    deque
+}
+
+/// Test every method on slices and `Vec`.
+#[test]
+fn slices() {
+    for &len in &[0, 1, 10, 100, 1000] {
+        let mut list = pairs(len);
+        list.timsort_by(|a, b| a.0.cmp(&b.0));
+        check_sorted(&list);
+        let mut list = pairs(len);
+        list[..].timsort_by_key(|a| a.0);
+        check_sorted(&list);
+        let mut list = pairs(len);
+        list.timsort_by_cached_key(|a| a.0);
+        check_sorted(&list);
+        let mut list = pairs(len);
+        list[..].timsort();
+        check_sorted(&list);
+    }
+}
+
+/// Test every method on deques that wrap around, and that the elements stay
+/// where they were in memory rather than being made contiguous.
+#[test]
+fn deques() {
+    for &len in &[0, 1, 2, 10, 100, 1000] {
+        for &front in &[0, len.min(1), len / 3, len / 2, len] {
+            let sorts: [fn(&mut Deque); 4] = [
+                |d| d.timsort_by(|a, b| a.0.cmp(&b.0)),
+                |d| d.timsort_by_key(|a| a.0),
+                |d| d.timsort_by_cached_key(|a| a.0),
+                |d| d.timsort(),
+            ];
+            for sort in &sorts {
+                let mut deque = wrapped(len, front);
+                let halves = (deque.as_slices().0.len(), deque.as_slices().1.len());
+                sort(&mut deque);
+                check_sorted(&deque);
+                assert_eq!((deque.as_slices().0.len(), deque.as_slices().1.len()), halves);
+            }
+        }
+    }
+}
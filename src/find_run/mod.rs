@@ -4,38 +4,43 @@
 #[cfg(test)]
 mod tests;
 
-use std::cmp::Ordering;
+use core::cmp::Ordering;
+use observer::SortObserver;
+use moves::Moves;
 
-/// Find a run, reversing if necessary.
-pub fn get_run<T, C: Fn(&T, &T) -> Ordering>(list: &mut [T], c: C) -> usize {
-    let (ord, len) = find_run(list, c);
+/// Find a run, reversing if necessary. Returns true if it was reversed, and
+/// its length. The elements moved by reversing it are reported to `o`, and
+/// the reversal itself to `m`.
+pub fn get_run<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>, O: SortObserver + ?Sized, M: Moves + ?Sized>(list: &mut [T], c: C, o: &mut O, m: &mut M) -> Result<(bool, usize), E> {
+    let (ord, len) = find_run(list, c)?;
     if ord {
         list.split_at_mut(len).0.reverse();
+        m.reverse(0, len);
+        o.moved(len - len % 2);
     }
-    len
+    Ok((ord, len))
 }
 
 
 /// Find a run. Returns true if it needs reversed, and false otherwise.
-pub fn find_run<T, C: Fn(&T, &T) -> Ordering>(list: &[T], c: C) -> (bool, usize) {
+pub fn find_run<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>>(list: &[T], mut c: C) -> Result<(bool, usize), E> {
     let list_len = list.len();
     if list_len < 2 {
-        return (false, list_len);
+        return Ok((false, list_len));
     }
     let mut pos = 1;
-    match c(&list[1], &list[0]) {
+    match c(&list[1], &list[0])? {
         Ordering::Less => {
-            while pos < list_len - 1 && c(&list[pos + 1], &list[pos]) == Ordering::Less {
+            while pos < list_len - 1 && c(&list[pos + 1], &list[pos])? == Ordering::Less {
                 pos += 1;
             }
-            (true, pos + 1)
+            Ok((true, pos + 1))
         },
         _ => {
-            while pos < list_len - 1 && c(&list[pos + 1], &list[pos]) != Ordering::Less {
+            while pos < list_len - 1 && c(&list[pos + 1], &list[pos])? != Ordering::Less {
                 pos += 1;
             }
-            (false, pos + 1)
+            Ok((false, pos + 1))
         }
     }
 }
-
@@ -1,24 +1,25 @@
+use std::vec::Vec;
 use find_run;
 
 #[test]
 fn empty() {
     let list: Vec<usize> = vec![];
     let (ord, len) = find_run(&list);
-    assert_eq!(ord, false);
+    assert!(!ord);
     assert_eq!(len, 0);
 }
 
 #[test]
 fn single() {
-    let (ord, len) = find_run(&vec![1]);
-    assert_eq!(ord, false);
+    let (ord, len) = find_run(&[1]);
+    assert!(!ord);
     assert_eq!(len, 1);
 }
 
 #[test]
 fn greater() {
-    let (ord, len) = find_run(&vec![1, 2, 2, 3, 4, 5]);
-    assert_eq!(ord, false);
+    let (ord, len) = find_run(&[1, 2, 2, 3, 4, 5]);
+    assert!(!ord);
     assert_eq!(len, 6);
 }
 
@@ -26,22 +27,22 @@ fn greater() {
 // less ordering. Unfortunately, reversing those sub-runs creates an unstable sort.
 #[test]
 fn less_stable() {
-    let (ord, len) = find_run(&vec![5, 4, 4, 3, 4, 5]);
-    assert_eq!(ord, true);
+    let (ord, len) = find_run(&[5, 4, 4, 3, 4, 5]);
+    assert!(ord);
     assert_eq!(len, 2);
 }
 
 #[test]
 fn less() {
-    let (ord, len) = find_run(&vec![5, 4, 3, 2, 1, 0]);
-    assert_eq!(ord, true);
+    let (ord, len) = find_run(&[5, 4, 3, 2, 1, 0]);
+    assert!(ord);
     assert_eq!(len, 6);
 }
 
 #[test]
 fn equal() {
-    let (ord, len) = find_run(&vec![2, 2, 2, 2, 2, 2]);
-    assert_eq!(ord, false);
+    let (ord, len) = find_run(&[2, 2, 2, 2, 2, 2]);
+    assert!(!ord);
     assert_eq!(len, 6);
 }
 
@@ -72,12 +73,12 @@ fn get_run_noreverse() {
 
 /// With comparator.
 pub fn find_run<T: Ord>(list: &[T]) -> (bool, usize) {
-    find_run::find_run(list, |a, b| a.cmp(b))
+    find_run::find_run(list, |a, b| Ok::<_, ()>(a.cmp(b))).unwrap()
 }
 
 
 /// With comparator.
 pub fn get_run<T: Ord>(list: &mut [T]) -> usize {
-    find_run::get_run(list, |a, b| a.cmp(b))
+    find_run::get_run(list, |a, b| Ok::<_, ()>(a.cmp(b)), &mut (), &mut ()).unwrap().1
 }
 
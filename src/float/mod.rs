@@ -0,0 +1,116 @@
+//! Sorting types that only have a partial order, like floats, where some
+//! values (NaNs) can't be compared with anything.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::Ordering;
+use core::fmt;
+use sort::{sort, try_sort};
+
+/// A floating point type, as sorted by `sort_floats`.
+pub trait Float: Copy {
+    /// Compares two values using the IEEE 754 `totalOrder` predicate.
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+impl Float for f32 {
+    fn total_cmp(&self, other: &f32) -> Ordering {
+        f32::total_cmp(self, other)
+    }
+}
+
+impl Float for f64 {
+    fn total_cmp(&self, other: &f64) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+}
+
+/// Sorts floats using the IEEE 754 `totalOrder` predicate, which gives every
+/// value, NaNs included, a place.
+///
+/// Negative NaNs go first and positive NaNs go last, and `-0.0` goes before
+/// `0.0`. The sort is stable, so NaNs with the same bits keep their order.
+///
+/// ```
+/// let mut list = [2.0, f64::NAN, -1.0, f64::INFINITY, 0.0, -0.0];
+/// timsort::sort_floats(&mut list);
+/// assert_eq!(&list[..5], &[-1.0, -0.0, 0.0, 2.0, f64::INFINITY]);
+/// assert!(list[5].is_nan());
+/// ```
+pub fn sort_floats<F: Float>(list: &mut [F]) {
+    sort(list, F::total_cmp)
+}
+
+/// Where `sort_with_nan_policy` puts values that can't be compared.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NanPolicy {
+    /// Put them at the start of the list.
+    First,
+    /// Put them at the end of the list.
+    Last,
+    /// Stop sorting, and return `Unordered`.
+    Error,
+}
+
+/// The error returned by `sort_with_nan_policy` with `NanPolicy::Error`,
+/// when two values in the list couldn't be compared.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Unordered;
+
+impl fmt::Display for Unordered {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the list holds values that can't be compared")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Unordered {}
+
+/// Sorts a list of values that only have a partial order.
+///
+/// A value that can't be compared with itself, such as NaN, is treated as a
+/// NaN, and goes wherever `policy` says. Stable, just like `sort_by`, so NaNs
+/// keep their order too. Two other values that can't be compared with each
+/// other are treated as equal, unless `policy` is `NanPolicy::Error`.
+///
+/// With `NanPolicy::Error`, the sort stops at the first pair of values that
+/// can't be compared, and the list is left unsorted, as it is by
+/// `try_sort_by`. The other policies never fail.
+///
+/// ```
+/// use timsort::NanPolicy;
+/// let mut list = [3.0, f64::NAN, 1.0];
+/// timsort::sort_with_nan_policy(&mut list, NanPolicy::First).unwrap();
+/// assert!(list[0].is_nan() && list[1] == 1.0 && list[2] == 3.0);
+/// assert!(timsort::sort_with_nan_policy(&mut list, NanPolicy::Error).is_err());
+/// ```
+pub fn sort_with_nan_policy<T: PartialOrd>(list: &mut [T], policy: NanPolicy) -> Result<(), Unordered> {
+    try_sort(list, |a, b| nan_cmp(a, b, policy))
+}
+
+/// Compares two values that might not be comparable, putting values that
+/// can't be compared with themselves wherever `policy` says.
+pub fn nan_cmp<T: PartialOrd>(a: &T, b: &T, policy: NanPolicy) -> Result<Ordering, Unordered> {
+    if let Some(ordering) = a.partial_cmp(b) {
+        return Ok(ordering);
+    }
+    let (a_nan, b_nan) = (is_nan(a), is_nan(b));
+    match policy {
+        NanPolicy::Error => Err(Unordered),
+        _ if a_nan == b_nan => Ok(Ordering::Equal),
+        NanPolicy::First => Ok(if a_nan { Ordering::Less } else { Ordering::Greater }),
+        NanPolicy::Last => Ok(if a_nan { Ordering::Greater } else { Ordering::Less }),
+    }
+}
+
+/// Compares two values with `NanPolicy::Last`, which is how everything in the
+/// crate that sorts by `PartialOrd` treats NaNs.
+pub fn nan_last_cmp<T: PartialOrd>(a: &T, b: &T) -> Ordering {
+    // Only `NanPolicy::Error` can fail.
+    nan_cmp(a, b, NanPolicy::Last).unwrap_or(Ordering::Equal)
+}
+
+fn is_nan<T: PartialOrd>(x: &T) -> bool {
+    x.partial_cmp(x).is_none()
+}
@@ -0,0 +1,104 @@
+//! Sorting types that only have a partial order, like floats.
+
+use std::vec::Vec;
+use std::{f32, f64};
+use float::{sort_floats, sort_with_nan_policy, NanPolicy, Unordered};
+
+/// Floats mixed with NaNs, infinities and zeroes of both signs.
+fn floats() -> Vec<f64> {
+    let mut list = Vec::new();
+    for i in 0..500 {
+        list.push(match i % 7 {
+            0 => f64::NAN,
+            1 => -0.0,
+            2 => 0.0,
+            3 => f64::INFINITY,
+            4 => f64::NEG_INFINITY,
+            _ => ((i * 7919) % 101) as f64 - 50.0,
+        });
+    }
+    list
+}
+
+/// Test that floats come out in `totalOrder`, NaNs included.
+#[test]
+fn total_order() {
+    let mut list = floats();
+    list.push(-f64::NAN);
+    sort_floats(&mut list);
+    assert!(list[0].is_nan() && list[0].is_sign_negative());
+    assert!(list[list.len() - 1].is_nan() && list[list.len() - 1].is_sign_positive());
+    for i in 1..list.len() {
+        assert!(list[i - 1].total_cmp(&list[i]) != core::cmp::Ordering::Greater);
+    }
+    let mut list: Vec<f32> = vec![1.0, f32::NAN, -0.0, 0.0, -1.0];
+    sort_floats(&mut list);
+    assert_eq!(list[..4].iter().map(|x| x.to_bits()).collect::<Vec<_>>(), [-1.0f32, -0.0, 0.0, 1.0].iter().map(|x| x.to_bits()).collect::<Vec<_>>());
+    assert!(list[4].is_nan());
+}
+
+/// Test that NaNs go first or last, keeping their order, and that the rest is
+/// sorted.
+#[test]
+fn nans_first_and_last() {
+    // Tag each value with its index, so stability can be checked.
+    let list: Vec<(f64, usize)> = floats().into_iter().enumerate().map(|(i, x)| (x, i)).collect();
+    let nans = list.iter().filter(|x| x.0.is_nan()).count();
+    assert!(nans > 0);
+    for &policy in &[NanPolicy::First, NanPolicy::Last] {
+        let mut floats: Vec<f64> = list.iter().map(|x| x.0).collect();
+        sort_with_nan_policy(&mut floats, policy).unwrap();
+        let (nan, rest) = if policy == NanPolicy::First {
+            floats.split_at(nans)
+        } else {
+            let (rest, nan) = floats.split_at(floats.len() - nans);
+            (nan, rest)
+        };
+        assert!(nan.iter().all(|x| x.is_nan()));
+        for i in 1..rest.len() {
+            assert!(rest[i - 1] <= rest[i]);
+        }
+        let mut pairs = list.clone();
+        sort_with_nan_policy(&mut pairs, policy).unwrap();
+        let nan: Vec<usize> = pairs.iter().filter(|x| x.0.is_nan()).map(|x| x.1).collect();
+        assert!(nan.windows(2).all(|w| w[0] < w[1]));
+    }
+}
+
+/// Test that the error policy fails when there is a NaN, and only then.
+#[test]
+fn nan_error() {
+    let mut list = floats();
+    assert_eq!(sort_with_nan_policy(&mut list, NanPolicy::Error), Err(Unordered));
+    assert_eq!(list.len(), 500);
+    assert_eq!(list.iter().filter(|x| x.is_nan()).count(), 72);
+    let mut list: Vec<f64> = floats().into_iter().filter(|x| !x.is_nan()).collect();
+    assert_eq!(sort_with_nan_policy(&mut list, NanPolicy::Error), Ok(()));
+    for i in 1..list.len() {
+        assert!(list[i - 1] <= list[i]);
+    }
+}
+
+/// Test that everything else that sorts by `PartialOrd` puts NaNs last too.
+#[test]
+fn nans_last_everywhere() {
+    use sorter::TimSorter;
+    let mut list = [3.0, f64::NAN, 1.0, 2.0];
+    TimSorter::new().sort(&mut list);
+    assert_eq!(list[..3], [1.0, 2.0, 3.0]);
+    assert!(list[3].is_nan());
+    let mut list = [3.0, f64::NAN, 1.0, 2.0];
+    ::sort(&mut list);
+    assert_eq!(list[..3], [1.0, 2.0, 3.0]);
+    assert!(list[3].is_nan());
+    let (mut a, mut b) = (vec![1.0, f64::NAN], vec![2.0, 0.5]);
+    ::sort(&mut a);
+    ::sort(&mut b);
+    let mut out = Vec::new();
+    ::merge_into(&a, &b, &mut out);
+    let merged = ::merge_vecs(a, b);
+    for list in &[out, merged] {
+        assert_eq!(list[..3], [0.5, 1.0, 2.0]);
+        assert!(list[3].is_nan());
+    }
+}
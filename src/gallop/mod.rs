@@ -3,7 +3,7 @@
 #[cfg(test)]
 mod tests;
 
-use std::cmp::Ordering;
+use core::cmp::{min, Ordering};
 
 #[derive(Copy, Clone)]
 pub enum Mode {
@@ -13,18 +13,18 @@ pub enum Mode {
 
 /// Returns the index where key should be inserted, assuming it shoul be placed
 /// at the beginning of any cluster of equal items.
-pub fn gallop_left<T, C: Fn(&T, &T) -> Ordering>(key: &T, list: &[T], mode: Mode, c: C) -> usize {
-    let (mut base, mut lim) = gallop(key, list, mode, &c);
+pub fn gallop_left<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>>(key: &T, list: &[T], mode: Mode, mut c: C) -> Result<usize, E> {
+    let (mut base, mut lim) = gallop(key, list, mode, true, &mut c)?;
     while lim != 0 {
         let ix = base + (lim / 2);
-        match c(&list[ix], key) {
+        match c(&list[ix], key)? {
             Ordering::Less => {
                 base = ix + 1;
                 lim -= 1;
             },
             Ordering::Greater => (),
             Ordering::Equal => {
-                if ix == 0 || c(&list[ix - 1], key) == Ordering::Less {
+                if ix == 0 || c(&list[ix - 1], key)? == Ordering::Less {
                     base = ix;
                     break;
                 }
@@ -32,17 +32,17 @@ pub fn gallop_left<T, C: Fn(&T, &T) -> Ordering>(key: &T, list: &[T], mode: Mode
         };
         lim /= 2;
     }
-    base
+    Ok(base)
 }
 
 /// Returns the index where key should be inserted, assuming it shoul be placed
 /// at the end of any cluster of equal items.
-pub fn gallop_right<T, C: Fn(&T, &T) -> Ordering>(key: &T, list: &[T], mode: Mode, c: C) -> usize {
+pub fn gallop_right<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>>(key: &T, list: &[T], mode: Mode, mut c: C) -> Result<usize, E> {
     let list_len = list.len();
-    let (mut base, mut lim) = gallop(key, list, mode, &c);
+    let (mut base, mut lim) = gallop(key, list, mode, false, &mut c)?;
     while lim != 0 {
         let ix = base + (lim / 2);
-        match c(&list[ix], key) {
+        match c(&list[ix], key)? {
             Ordering::Less => {
                 base = ix + 1;
                 lim -= 1;
@@ -50,7 +50,7 @@ pub fn gallop_right<T, C: Fn(&T, &T) -> Ordering>(key: &T, list: &[T], mode: Mod
             Ordering::Greater => (),
             Ordering::Equal => {
                 base = ix + 1;
-                if ix == list_len - 1 || c(&list[ix + 1], key) == Ordering::Greater {
+                if ix == list_len - 1 || c(&list[ix + 1], key)? == Ordering::Greater {
                     break;
                 } else {
                     lim -= 1;
@@ -59,59 +59,106 @@ pub fn gallop_right<T, C: Fn(&T, &T) -> Ordering>(key: &T, list: &[T], mode: Mod
         };
         lim /= 2;
     }
-    base
+    Ok(base)
+}
+
+/// Returns the index where key should be inserted, starting the search at
+/// `hint` and galloping outwards from there, the way CPython's `gallop_left`
+/// and `gallop_right` do. The closer the answer is to the hint, the fewer
+/// comparisons it takes; without a hint, it is a plain binary search.
+///
+/// When `left` is true, the key goes before any equal items, as it does for
+/// `gallop_left`; otherwise, it goes after them. A hint past the end of the
+/// list is treated as the last element.
+pub fn gallop_from<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>>(key: &T, list: &[T], hint: Option<usize>, left: bool, mut c: C) -> Result<usize, E> {
+    let list_len = list.len();
+    let mut goes_before = |item: &T| -> Result<bool, E> {
+        Ok(match c(item, key)? {
+            Ordering::Less => true,
+            Ordering::Equal => !left,
+            Ordering::Greater => false,
+        })
+    };
+    // The answer is somewhere in `lo..=hi`.
+    let (mut lo, mut hi) = (0, list_len);
+    if let Some(hint) = hint {
+        if list_len == 0 {
+            return Ok(0);
+        }
+        let hint = min(hint, list_len - 1);
+        let mut ofs = 1;
+        if goes_before(&list[hint])? {
+            lo = hint + 1;
+            while hint + ofs < list_len && goes_before(&list[hint + ofs])? {
+                lo = hint + ofs + 1;
+                ofs = ofs * 2 + 1;
+            }
+            hi = min(hint + ofs, list_len);
+        } else {
+            hi = hint;
+            while ofs <= hint && !goes_before(&list[hint - ofs])? {
+                hi = hint - ofs;
+                ofs = ofs * 2 + 1;
+            }
+            lo = if ofs <= hint { hint - ofs + 1 } else { 0 };
+        }
+    }
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if goes_before(&list[mid])? {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    Ok(lo)
 }
 
 
-fn gallop<T, C: Fn(&T, &T) -> Ordering>(key: &T, list: &[T], mode: Mode, c: C) -> (usize, usize) {
+/// Narrows down where key should be inserted, by comparing it against elements
+/// at growing distances from one end of the list. Returns the `(base, lim)`
+/// window that the binary search then finishes off.
+///
+/// When `left` is true, the key goes before any equal items, as it does for
+/// `gallop_left`; otherwise, it goes after them.
+fn gallop<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>>(key: &T, list: &[T], mode: Mode, left: bool, mut c: C) -> Result<(usize, usize), E> {
     let list_len = list.len();
     if list_len == 0 {
-        return (0, 0);
+        return Ok((0, 0));
     }
+    let mut goes_before = |item: &T| -> Result<bool, E> {
+        Ok(match c(item, key)? {
+            Ordering::Less => true,
+            Ordering::Equal => !left,
+            Ordering::Greater => false,
+        })
+    };
     match mode {
         Mode::Forward => {
             let mut prev_val = 0;
             let mut next_val = 1;
-            while next_val < list_len {
-                match c(&list[next_val], key) {
-                    Ordering::Less => {
-                        prev_val = next_val;
-                        next_val = ((next_val + 1) * 2) - 1;
-                    },
-                    Ordering::Greater => {
-                        break;
-                    },
-                    Ordering::Equal => {
-                        next_val += 1;
-                        break;
-                    },
-                }
+            while next_val < list_len && goes_before(&list[next_val])? {
+                prev_val = next_val;
+                next_val = ((next_val + 1) * 2) - 1;
             }
             if next_val > list_len {
                 next_val = list_len;
             }
-            (prev_val, next_val - prev_val)
+            Ok((prev_val, next_val - prev_val))
         },
         Mode::Reverse => {
             let mut prev_val = list_len;
             let mut next_val = ((prev_val + 1) / 2) - 1;
-            loop {
-                match c(&list[next_val], key) {
-                    Ordering::Greater => {
-                        prev_val = next_val + 1;
-                        next_val = (next_val + 1) / 2;
-                        if next_val != 0 {
-                            next_val -= 1;
-                        } else {
-                            break;
-                        }
-                    },
-                    Ordering::Less | Ordering::Equal => {
-                        break;
-                    },
+            while !goes_before(&list[next_val])? {
+                prev_val = next_val + 1;
+                next_val = (next_val + 1) / 2;
+                if next_val != 0 {
+                    next_val -= 1;
+                } else {
+                    break;
                 }
             }
-            (next_val, prev_val - next_val)
+            Ok((next_val, prev_val - next_val))
         }
     }
 }
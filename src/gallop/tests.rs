@@ -1,11 +1,12 @@
 use gallop::{self, Mode};
+use test_util::pseudo_random;
 
 macro_rules! test_both {
     ($v:ident, $($x:expr);*) => {{
         let $v = Mode::Forward;
-        $($x;)*;
+        $($x;)*
         let $v = Mode::Reverse;
-        $($x;)*;
+        $($x;)*
     }}
 }
 
@@ -181,10 +182,29 @@ fn gallop_large_end_greater() {
 }
 
 pub fn gallop_left<T: Ord>(key: &T, list: &[T], mode: Mode) -> usize {
-    gallop::gallop_left(key, list, mode, |a, b| a.cmp(b) )
+    gallop::gallop_left(key, list, mode, |a, b| Ok::<_, ()>(a.cmp(b)) ).unwrap()
 }
 
 pub fn gallop_right<T: Ord>(key: &T, list: &[T], mode: Mode) -> usize {
-    gallop::gallop_right(key, list, mode, |a, b| a.cmp(b) )
+    gallop::gallop_right(key, list, mode, |a, b| Ok::<_, ()>(a.cmp(b)) ).unwrap()
 }
 
+
+/// Check every insertion point in short lists with lots of equal items.
+#[test]
+fn gallop_runs_of_equal() {
+    for len in 0..40usize {
+        for seed in 0..20usize {
+            let mut list = pseudo_random(len, 7, seed);
+            list.sort();
+            for key in 0..8 {
+                let lb = list.iter().filter(|&&x| x < key).count();
+                let ub = list.iter().filter(|&&x| x <= key).count();
+                test_both!{mode,
+                    assert_eq!(gallop_left(&key, &list, mode), lb);
+                    assert_eq!(gallop_right(&key, &list, mode), ub)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Sorting large elements by sorting their indices, and then moving each
+//! element straight to where it belongs.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::ptr;
+use alloc::vec::Vec;
+use policy::Powersort;
+use sort::{try_sort_in, RunStack};
+
+/// Elements at least this many bytes long are sorted indirectly by `sort_by`
+/// and `try_sort_by`. Below this, moving the elements themselves around is
+/// cheaper than the extra work of going through their indices.
+///
+/// The functions built on those two get the same treatment: `sort`,
+/// `sort_by_key`, `sort_by_key_ref`, `sort_floats`, `sort_with_nan_policy`,
+/// the `TimSortExt` methods (though a `VecDeque` then merges its two halves
+/// by moving the elements), and `par_sort_by` on lists too short to be split
+/// between threads. `sort_by_cached_key` sorts its keys instead, and moves
+/// each element into place afterwards. Everything else always moves the
+/// elements themselves: `TimSorter`, `sort_by_with_buffer`,
+/// `sort_by_with_stats`, `sort_by_with_observer`, `sort_by_checked`,
+/// `par_sort_by` once it has split the list up, and `ZipSort`. Where one of
+/// those would be used for large elements, `sort_by_indirect` can be called
+/// instead.
+pub const INDIRECT_SIZE: usize = 128;
+
+/// Sorts the list by sorting a list of indices into it, comparing the elements
+/// they point at with `c`, and then moving every element into place.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+/// The merges and insertion sorts only ever move indices around, and then each
+/// element is moved once, apart from one per cycle of the permutation, which
+/// is moved twice. That is much less copying for large elements, at the cost
+/// of a `usize` of memory per element. `sort_by` does this by itself for
+/// elements of at least `INDIRECT_SIZE` bytes.
+pub fn sort_indirect<T, C: FnMut(&T, &T) -> Ordering>(list: &mut [T], mut c: C) {
+    match try_sort_indirect(list, |a, b| Ok::<Ordering, Infallible>(c(a, b))) {
+        Ok(()) => (),
+        Err(e) => match e {},
+    }
+}
+
+/// Sorts the list indirectly, with a comparator that can fail. If it does,
+/// the list is left exactly as it was.
+pub fn try_sort_indirect<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>>(list: &mut [T], mut c: C) -> Result<(), E> {
+    let mut perm: Vec<usize> = (0..list.len()).collect();
+    try_sort_in(&mut perm, |&i, &j| c(&list[i], &list[j]), &mut Powersort, &mut RunStack::new(), &mut Vec::new(), &mut (), &mut ())?;
+    unsafe {
+        permute(list, &mut perm);
+    }
+    Ok(())
+}
+
+/// Moves the element at `perm[i]` to `i`, following each cycle of the
+/// permutation round. `perm` is used up keeping track of which elements are
+/// in place.
+///
+/// `perm` must be a permutation of `0..list.len()`. Nothing in here can panic,
+/// so the list can't be left with an element missing or doubled up.
+pub unsafe fn permute<T>(list: &mut [T], perm: &mut [usize]) {
+    debug_assert_eq!(list.len(), perm.len());
+    let len = list.len();
+    let list = list.as_mut_ptr();
+    for start in 0..len {
+        if perm[start] == start {
+            continue;
+        }
+        // Take the first element of the cycle out, pull each of the others
+        // into the hole left by the one before, and put it back at the end.
+        let tmp = ptr::read(list.add(start));
+        let mut i = start;
+        loop {
+            let next = perm[i];
+            debug_assert!(next < len);
+            perm[i] = i;
+            if next == start {
+                ptr::write(list.add(i), tmp);
+                break;
+            }
+            ptr::copy_nonoverlapping(list.add(next), list.add(i), 1);
+            i = next;
+        }
+    }
+}
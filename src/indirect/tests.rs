@@ -0,0 +1,70 @@
+//! Sorting large elements by sorting their indices.
+
+use std::string::{String, ToString};
+use std::vec::Vec;
+use core::cmp::Ordering;
+use core::mem::size_of;
+use indirect::{sort_indirect, try_sort_indirect, INDIRECT_SIZE};
+use sort::{sort, try_sort};
+use test_util::{check_sorted_by, pseudo_random};
+
+/// An element big enough to be sorted indirectly, that owns memory so that
+/// a lost or duplicated element would be noticed.
+struct Big {
+    key: usize,
+    index: usize,
+    name: String,
+    _padding: [u64; 16],
+}
+
+fn bigs(len: usize) -> Vec<Big> {
+    pseudo_random(len, 101, 1).into_iter().enumerate().map(|(i, key)| Big {
+        key,
+        index: i,
+        name: i.to_string(),
+        _padding: [0; 16],
+    }).collect()
+}
+
+fn check(list: &[Big], len: usize) {
+    assert_eq!(list.len(), len);
+    check_sorted_by(list, |x| (x.key, x.index));
+    for x in list {
+        assert_eq!(x.name, x.index.to_string());
+    }
+}
+
+/// Test sorting indirectly by asking for it, and by `sort` choosing it.
+#[test]
+fn sorted_and_stable() {
+    assert!(size_of::<Big>() >= INDIRECT_SIZE);
+    for &len in &[0, 1, 2, 10, 63, 64, 100, 1000] {
+        let mut list = bigs(len);
+        sort_indirect(&mut list, |a, b| a.key.cmp(&b.key));
+        check(&list, len);
+        let mut list = bigs(len);
+        sort(&mut list, |a, b| a.key.cmp(&b.key));
+        check(&list, len);
+        // Already sorted, and backwards.
+        sort_indirect(&mut list, |a, b| a.key.cmp(&b.key));
+        check(&list, len);
+        sort(&mut list, |a, b| b.index.cmp(&a.index));
+        assert!(list.iter().rev().enumerate().all(|(i, x)| x.index == i));
+    }
+}
+
+/// Test that a failing comparator leaves the list as it was, since nothing is
+/// moved until the indices are sorted.
+#[test]
+fn error() {
+    let mut list = bigs(1000);
+    let mut count = 0;
+    let result = try_sort_indirect(&mut list, |a, b| {
+        count += 1;
+        if count == 2000 { Err(()) } else { Ok(a.key.cmp(&b.key)) }
+    });
+    assert_eq!(result, Err(()));
+    assert!(list.iter().enumerate().all(|(i, x)| x.index == i));
+    assert_eq!(try_sort(&mut list, |_, _| Err::<Ordering, _>("stop")), Err("stop"));
+    assert!(list.iter().enumerate().all(|(i, x)| x.index == i));
+}
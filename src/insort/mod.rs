@@ -4,29 +4,83 @@
 #[cfg(test)]
 mod tests;
 
-use std::cmp::Ordering;
-use std::ptr;
+use core::cmp::Ordering;
+use core::ptr;
+use observer::SortObserver;
+use moves::Moves;
 
 /// Sorts the list using insertion sort.
 ///
 /// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+/// If it returns an error, sorting stops and the list is left as a permutation
+/// of its original contents. Every element moved is reported to `o`, and
+/// every insertion to `m`.
 // This version was almost completely copied from libcollections/slice.rs
-pub fn sort<T, C: Fn(&T, &T) -> Ordering>(list: &mut [T], c: C) {
+pub fn sort<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>, O: SortObserver + ?Sized, M: Moves + ?Sized>(list: &mut [T], mut c: C, o: &mut O, m: &mut M) -> Result<(), E> {
     unsafe {
         let list_ptr = list.as_mut_ptr();
         let len = list.len();
         for i in 0..len {
             let mut j = i;
-            let list_i = list_ptr.offset(i as isize);
-            while j > 0 && c(&*list_i, &*list_ptr.offset(j as isize - 1)) == Ordering::Less {
+            let list_i = list_ptr.add(i);
+            // All of the comparisons happen before anything is moved, so
+            // bailing out here cannot leave a hole in the list.
+            while j > 0 && c(&*list_i, &*list_ptr.add(j - 1))? == Ordering::Less {
                 j -= 1;
             }
             if i != j {
-                let list_j = list_ptr.offset(j as isize);
+                let list_j = list_ptr.add(j);
                 let tmp = ptr::read(list_i);
                 ptr::copy(list_j, list_j.offset(1), i - j);
                 ptr::write(list_j, tmp);
+                m.rotate(j, i, i + 1);
+                o.moved(i - j + 1);
             }
         }
     }
+    Ok(())
+}
+
+/// Sorts the list using binary insertion sort, given that the first `start`
+/// elements are already sorted.
+///
+/// Each insertion point is found with a binary search, so this makes
+/// O(n log n) comparisons instead of O(n²), although it still moves O(n²)
+/// elements. That is a good trade when comparing is expensive. Equal elements
+/// are inserted after each other, so the sort is stable.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+/// If it returns an error, sorting stops and the list is left as a permutation
+/// of its original contents. Every element moved is reported to `o`, and
+/// every insertion to `m`.
+pub fn binary_sort<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>, O: SortObserver + ?Sized, M: Moves + ?Sized>(list: &mut [T], start: usize, mut c: C, o: &mut O, m: &mut M) -> Result<(), E> {
+    let len = list.len();
+    debug_assert!(start <= len);
+    unsafe {
+        let list_ptr = list.as_mut_ptr();
+        for i in start.max(1)..len {
+            let list_i = list_ptr.add(i);
+            // Find the first element of the sorted prefix that goes after
+            // this one. As above, nothing moves until it has been found.
+            let mut lo = 0;
+            let mut hi = i;
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                if c(&*list_i, &*list_ptr.add(mid))? == Ordering::Less {
+                    hi = mid;
+                } else {
+                    lo = mid + 1;
+                }
+            }
+            if lo != i {
+                let list_lo = list_ptr.add(lo);
+                let tmp = ptr::read(list_i);
+                ptr::copy(list_lo, list_lo.add(1), i - lo);
+                ptr::write(list_lo, tmp);
+                m.rotate(lo, i, i + 1);
+                o.moved(i - lo + 1);
+            }
+        }
+    }
+    Ok(())
 }
@@ -1,11 +1,13 @@
+use std::vec::Vec;
 use insort;
+use test_util::{pseudo_random, tagged};
 
 /// Test the insertion sort implementation with an empty list
 #[test]
 fn empty() {
     let mut list: Vec<u32> = vec![];
     sort(&mut list);
-    assert!(list.len() == 0);
+    assert!(list.is_empty());
 }
 
 /// Test the insertion sort implementation with a single-element list
@@ -59,18 +61,18 @@ fn stable() {
     struct Item {
         key1: usize,
         key2: usize,
-    };
+    }
     let mut list: Vec<Item> = (0..len).map(|_| {
         key1 += 1;
         key1 %= 5;
         key2 += 1;
         Item {
-            key1: key1,
-            key2: key2,
+            key1,
+            key2,
         }
     }).collect();
-    insort::sort(&mut list, |a, b| a.key1.cmp(&b.key1));
-    for i in (0 .. (len - 1)) {
+    insort::sort(&mut list, |a, b| Ok::<_, ()>(a.key1.cmp(&b.key1)), &mut (), &mut ()).unwrap();
+    for i in 0 .. (len - 1) {
         assert!(list[i].key1 <= list[i + 1].key1);
         if list[i].key1 == list[i + 1].key1 {
             assert!(list[i].key2 <= list[i + 1].key2);
@@ -78,8 +80,48 @@ fn stable() {
     }
 }
 
+/// Test the binary insertion sort with every length of sorted prefix.
+#[test]
+fn binary_prefix() {
+    for start in 0..9 {
+        let mut list = vec![2, 4, 4, 6, 8, 9, 7, 4, 1];
+        list[..start].sort();
+        insort::binary_sort(&mut list, start, |a, b| Ok::<_, ()>(a.cmp(b)), &mut (), &mut ()).unwrap();
+        assert_eq!(list, vec![1, 2, 4, 4, 4, 6, 7, 8, 9]);
+    }
+}
+
+/// Make sure the binary insertion sort is stable.
+#[test]
+fn binary_stable() {
+    let len = 256;
+    let mut list = tagged(pseudo_random(len, 5, 0));
+    insort::binary_sort(&mut list, 1, |a, b| Ok::<_, ()>(a.0.cmp(&b.0)), &mut (), &mut ()).unwrap();
+    for i in 0 .. (len - 1) {
+        assert!(list[i].0 <= list[i + 1].0);
+        if list[i].0 == list[i + 1].0 {
+            assert!(list[i].1 < list[i + 1].1);
+        }
+    }
+}
+
+/// Test that the binary insertion sort makes O(n log n) comparisons, and
+/// none at all for the sorted prefix.
+#[test]
+fn binary_comparisons() {
+    let len = 64;
+    let mut list: Vec<usize> = (0..len).rev().collect();
+    let mut comparisons = 0;
+    insort::binary_sort(&mut list, 1, |a, b| { comparisons += 1; Ok::<_, ()>(a.cmp(b)) }, &mut (), &mut ()).unwrap();
+    assert!(comparisons <= len * 6);
+    assert!(list.iter().enumerate().all(|(i, &x)| i == x));
+    let mut comparisons = 0;
+    insort::binary_sort(&mut list, len, |a, b| { comparisons += 1; Ok::<_, ()>(a.cmp(b)) }, &mut (), &mut ()).unwrap();
+    assert_eq!(comparisons, 0);
+}
+
 /// Insertion sort implementation convenience used for tests.
 pub fn sort<T: Ord>(list: &mut[T]) {
-    insort::sort(list, |a, b| a.cmp(b) );
+    insort::sort(list, |a, b| Ok::<_, ()>(a.cmp(b)), &mut (), &mut ()).unwrap();
 }
 
@@ -0,0 +1,57 @@
+//! Sorting by a key extracted from each element, rather than with a
+//! comparator that looks at the whole thing.
+
+#[cfg(test)]
+mod tests;
+
+use alloc::vec::Vec;
+use sort::sort;
+
+/// Sorts the list by the key that `f` extracts from each element.
+///
+/// `f` is called twice for every comparison, so it should be cheap. If it is
+/// not, use `sort_by_cached_key` instead.
+pub fn sort_by_key<T, K: Ord, F: FnMut(&T) -> K>(list: &mut [T], mut f: F) {
+    sort(list, |a, b| f(a).cmp(&f(b)));
+}
+
+/// Sorts the list by a key that is borrowed from each element.
+///
+/// Unlike `sort_by_key`, the key does not have to be owned, so a list can be
+/// sorted by a `String` field (or anything else that isn't `Copy`) without
+/// cloning it on every comparison.
+pub fn sort_by_key_ref<T, K: Ord + ?Sized, F: for<'a> FnMut(&'a T) -> &'a K>(list: &mut [T], mut f: F) {
+    sort(list, |a, b| f(a).cmp(f(b)));
+}
+
+/// Sorts the list by the key that `f` extracts from each element, calling `f`
+/// exactly once per element.
+///
+/// The keys are computed up front and sorted alongside the index of the
+/// element they came from. The elements are then moved into place by
+/// following the cycles of the resulting permutation.
+pub fn sort_by_cached_key<T, K: Ord, F: FnMut(&T) -> K>(list: &mut [T], f: F) {
+    if list.len() < 2 {
+        return;
+    }
+    let indices: Vec<(K, usize)> = list.iter().map(f).enumerate().map(|(i, k)| (k, i)).collect();
+    permute_by_keys(indices, |i, j| list.swap(i, j));
+}
+
+/// Sorts `indices` by key, then calls `swap` to move the element at each index
+/// to where its key ended up.
+pub fn permute_by_keys<K: Ord, S: FnMut(usize, usize)>(mut indices: Vec<(K, usize)>, mut swap: S) {
+    // The index doesn't need to be compared: the sort is stable, so equal
+    // keys keep their original order anyway.
+    sort(&mut indices, |a, b| a.0.cmp(&b.0));
+    for i in 0..indices.len() {
+        let mut index = indices[i].1;
+        // Everything before `i` has already been put into place, so if the
+        // element we want was there, it got swapped away. Follow it.
+        while index < i {
+            index = indices[index].1;
+        }
+        indices[i].1 = index;
+        swap(i, index);
+    }
+}
@@ -0,0 +1,72 @@
+use std::string::String;
+use std::vec::Vec;
+use key::{sort_by_key, sort_by_key_ref, sort_by_cached_key};
+use test_util::{check_sorted_by, pseudo_random};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+    name: String,
+    key2: usize,
+}
+
+fn items(len: usize) -> Vec<Item> {
+    (0..len).map(|i| Item {
+        name: format!("{}", (i * 7919) % 13),
+        key2: i,
+    }).collect()
+}
+
+fn check_sorted(list: &[Item]) {
+    check_sorted_by(list, |item| (&item.name[..], item.key2));
+}
+
+/// Test sorting by an owned key.
+#[test]
+fn by_key() {
+    let mut list = pseudo_random(300, 1009, 0);
+    sort_by_key(&mut list, |&x| 1009 - x);
+    for i in 0..(list.len() - 1) {
+        assert!(list[i] >= list[i + 1]);
+    }
+}
+
+/// Make sure sorting by key is stable.
+#[test]
+fn by_key_stable() {
+    let mut list = items(300);
+    sort_by_key(&mut list, |item| item.name.clone());
+    check_sorted(&list);
+}
+
+/// Make sure sorting by a borrowed key is stable.
+#[test]
+fn by_key_ref_stable() {
+    let mut list = items(300);
+    sort_by_key_ref(&mut list, |item| &item.name[..]);
+    check_sorted(&list);
+}
+
+/// Make sure sorting by a cached key is stable.
+#[test]
+fn by_cached_key_stable() {
+    for &len in &[0, 1, 2, 5, 63, 64, 300] {
+        let mut list = items(len);
+        sort_by_cached_key(&mut list, |item| item.name.clone());
+        check_sorted(&list);
+    }
+}
+
+/// Make sure the cached key is only computed once per element.
+#[test]
+fn by_cached_key_once() {
+    let mut list = pseudo_random(300, 1009, 0);
+    let mut calls = 0;
+    sort_by_cached_key(&mut list, |&x| {
+        calls += 1;
+        x
+    });
+    assert_eq!(calls, 300);
+    for i in 0..(list.len() - 1) {
+        assert!(list[i] <= list[i + 1]);
+    }
+}
@@ -0,0 +1,233 @@
+//! Merging any number of sorted inputs into one sorted output. The inputs are
+//! kept in a binary heap, ordered by their next element, and the one at the
+//! top is where the next element comes from.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::mem;
+use alloc::vec::Vec;
+use gallop::{gallop_left, gallop_right, Mode};
+
+/// Merges sorted iterators into one sorted iterator.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+/// Every input must already be sorted by `c`. The merge is stable: elements
+/// that compare equal come out in the order of the inputs they came from.
+///
+/// ```
+/// let merged: Vec<u32> = timsort::kmerge_by(vec![vec![1, 4], vec![2, 3], vec![]], |a, b| a.cmp(b)).collect();
+/// assert_eq!(merged, vec![1, 2, 3, 4]);
+/// ```
+pub fn kmerge_by<I: IntoIterator, J: IntoIterator<Item = I>, C: FnMut(&I::Item, &I::Item) -> Ordering>(iters: J, c: C) -> KMerge<I::IntoIter, C> {
+    let mut heap = Vec::new();
+    for (index, iter) in iters.into_iter().enumerate() {
+        let mut iter = iter.into_iter();
+        if let Some(item) = iter.next() {
+            heap.push(Head { item, index, iter });
+        }
+    }
+    let mut merge = KMerge { heap, c };
+    for i in (0..merge.heap.len() / 2).rev() {
+        merge.sift_down(i);
+    }
+    merge
+}
+
+/// Merges sorted slices, borrowing the elements instead of moving them.
+///
+/// This does the same thing as `kmerge_by`, but because it can see the whole
+/// of every input, it finds how many elements in a row come from the same one
+/// by galloping, instead of comparing them one by one. `next_slice` hands out
+/// each of those streaks at once, so they can be copied in bulk.
+///
+/// ```
+/// let mut merged = timsort::kmerge_slices_by(&[&[1, 2, 3][..], &[4, 5][..]], |a, b| a.cmp(b));
+/// assert_eq!(merged.next_slice(), Some(&[1, 2, 3][..]));
+/// assert_eq!(merged.next_slice(), Some(&[4, 5][..]));
+/// assert_eq!(merged.next_slice(), None);
+/// ```
+pub fn kmerge_slices_by<'a, T, C: FnMut(&T, &T) -> Ordering>(slices: &[&'a [T]], c: C) -> KMergeSlices<'a, T, C> {
+    let heap = slices.iter().enumerate().filter(|&(_, s)| !s.is_empty()).map(|(index, &s)| (s, index)).collect();
+    let mut merge = KMergeSlices { heap, c, streak: &[] };
+    for i in (0..merge.heap.len() / 2).rev() {
+        merge.sift_down(i);
+    }
+    merge
+}
+
+/// The iterator returned by `kmerge_by`.
+pub struct KMerge<I: Iterator, C> {
+    heap: Vec<Head<I>>,
+    c: C,
+}
+
+/// An input to `KMerge` that hasn't run out yet, with the next element taken
+/// out of it.
+struct Head<I: Iterator> {
+    item: I::Item,
+    /// Where the input was in the list passed to `kmerge_by`. Ties go to the
+    /// lowest.
+    index: usize,
+    iter: I,
+}
+
+impl<I: Iterator, C: FnMut(&I::Item, &I::Item) -> Ordering> KMerge<I, C> {
+    fn sift_down(&mut self, i: usize) {
+        let c = &mut self.c;
+        sift_down(&mut self.heap, i, |a, b| {
+            match c(&a.item, &b.item) {
+                Ordering::Less => true,
+                Ordering::Equal => a.index < b.index,
+                Ordering::Greater => false,
+            }
+        });
+    }
+}
+
+impl<I: Iterator, C: FnMut(&I::Item, &I::Item) -> Ordering> Iterator for KMerge<I, C> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        let item = match self.heap[0].iter.next() {
+            Some(next) => mem::replace(&mut self.heap[0].item, next),
+            None => self.heap.swap_remove(0).item,
+        };
+        if !self.heap.is_empty() {
+            self.sift_down(0);
+        }
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let mut lo = self.heap.len();
+        let mut hi = Some(self.heap.len());
+        for head in &self.heap {
+            let (l, h) = head.iter.size_hint();
+            lo = lo.saturating_add(l);
+            hi = match (hi, h) {
+                (Some(hi), Some(h)) => hi.checked_add(h),
+                _ => None,
+            };
+        }
+        (lo, hi)
+    }
+}
+
+/// The iterator returned by `kmerge_slices_by`.
+pub struct KMergeSlices<'a, T: 'a, C> {
+    /// What is left of each input, and where it was in the list of inputs.
+    /// Empty inputs are dropped.
+    heap: Vec<(&'a [T], usize)>,
+    c: C,
+    /// What is left of the streak `next` is working through.
+    streak: &'a [T],
+}
+
+impl<'a, T, C: FnMut(&T, &T) -> Ordering> KMergeSlices<'a, T, C> {
+    /// Returns the next elements of the merged output that all come from the
+    /// same input, as a slice of that input.
+    ///
+    /// Anything already returned by `next` is skipped.
+    pub fn next_slice(&mut self) -> Option<&'a [T]> {
+        if !self.streak.is_empty() {
+            return Some(mem::take(&mut self.streak));
+        }
+        let (first, index) = match self.heap.first() {
+            Some(&top) => top,
+            None => return None,
+        };
+        let len = if self.heap.len() == 1 {
+            first.len()
+        } else {
+            // Everything up to the next element of the runner up comes from
+            // the top input.
+            let (second, second_index) = if self.heap.len() > 2 && self.before(2, 1) { self.heap[2] } else { self.heap[1] };
+            let key = &second[0];
+            let c = &mut self.c;
+            let c = |a: &T, b: &T| Ok::<Ordering, Infallible>(c(a, b));
+            let found = if index < second_index {
+                gallop_right(key, &first[1..], Mode::Forward, c)
+            } else {
+                gallop_left(key, &first[1..], Mode::Forward, c)
+            };
+            match found {
+                Ok(n) => n + 1,
+                Err(e) => match e {},
+            }
+        };
+        let (streak, rest) = first.split_at(len);
+        if rest.is_empty() {
+            self.heap.swap_remove(0);
+        } else {
+            self.heap[0].0 = rest;
+        }
+        if !self.heap.is_empty() {
+            self.sift_down(0);
+        }
+        Some(streak)
+    }
+
+    /// Whether the input at `a` in the heap goes before the one at `b`.
+    fn before(&mut self, a: usize, b: usize) -> bool {
+        let ((a, a_index), (b, b_index)) = (self.heap[a], self.heap[b]);
+        match (self.c)(&a[0], &b[0]) {
+            Ordering::Less => true,
+            Ordering::Equal => a_index < b_index,
+            Ordering::Greater => false,
+        }
+    }
+
+    fn sift_down(&mut self, i: usize) {
+        let c = &mut self.c;
+        sift_down(&mut self.heap, i, |&(a, a_index), &(b, b_index)| {
+            match c(&a[0], &b[0]) {
+                Ordering::Less => true,
+                Ordering::Equal => a_index < b_index,
+                Ordering::Greater => false,
+            }
+        });
+    }
+}
+
+impl<'a, T, C: FnMut(&T, &T) -> Ordering> Iterator for KMergeSlices<'a, T, C> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.streak.is_empty() {
+            self.streak = self.next_slice()?;
+        }
+        let (first, rest) = self.streak.split_first()?;
+        self.streak = rest;
+        Some(first)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.heap.iter().fold(self.streak.len(), |len, &(s, _)| len + s.len());
+        (len, Some(len))
+    }
+}
+
+/// Moves the element at `i` down the heap until neither of its children go
+/// before it.
+fn sift_down<S, F: FnMut(&S, &S) -> bool>(heap: &mut [S], mut i: usize, mut before: F) {
+    loop {
+        let mut child = 2 * i + 1;
+        if child >= heap.len() {
+            return;
+        }
+        if child + 1 < heap.len() && before(&heap[child + 1], &heap[child]) {
+            child += 1;
+        }
+        if !before(&heap[child], &heap[i]) {
+            return;
+        }
+        heap.swap(i, child);
+        i = child;
+    }
+}
@@ -0,0 +1,80 @@
+//! Merging any number of sorted inputs into one sorted output.
+
+use std::vec::Vec;
+use kmerge::{kmerge_by, kmerge_slices_by};
+
+/// Sorted inputs of different lengths, each element tagged with which input
+/// it came from and where. Keys repeat within and between inputs.
+fn inputs(count: usize) -> Vec<Vec<(usize, usize, usize)>> {
+    (0..count).map(|i| {
+        let len = (i * 37) % 50;
+        let mut key = i % 3;
+        (0..len).map(|j| {
+            key += (i + j) % 4 / 2;
+            (key, i, j)
+        }).collect()
+    }).collect()
+}
+
+fn check_merged<'a, I: Iterator<Item = &'a (usize, usize, usize)>>(inputs: &[Vec<(usize, usize, usize)>], merged: I) {
+    let mut expected: Vec<&(usize, usize, usize)> = inputs.iter().flat_map(|v| v.iter()).collect();
+    // The inputs were concatenated in order, so a stable sort is the answer.
+    expected.sort_by_key(|x| x.0);
+    assert!(merged.eq(expected.into_iter()));
+}
+
+/// Test that merging iterators is sorted and stable.
+#[test]
+fn iters() {
+    for &count in &[0, 1, 2, 3, 10, 33] {
+        let inputs = inputs(count);
+        check_merged(&inputs, kmerge_by(inputs.iter().map(|v| v.iter()), |a, b| a.0.cmp(&b.0)));
+    }
+}
+
+/// Test that merging slices is sorted and stable, and that `next_slice` and
+/// `next` can be mixed.
+#[test]
+fn slices() {
+    for &count in &[0, 1, 2, 3, 10, 33] {
+        let inputs = inputs(count);
+        let slices: Vec<&[(usize, usize, usize)]> = inputs.iter().map(|v| &v[..]).collect();
+        check_merged(&inputs, kmerge_slices_by(&slices, |a, b| a.0.cmp(&b.0)));
+        let mut merged = kmerge_slices_by(&slices, |a, b| a.0.cmp(&b.0));
+        let mut out = Vec::new();
+        while let Some(streak) = merged.next_slice() {
+            assert!(streak.iter().all(|x| x.1 == streak[0].1));
+            out.extend(streak.iter());
+            out.extend(merged.next());
+        }
+        check_merged(&inputs, out.into_iter());
+    }
+}
+
+/// Test that inputs that don't overlap come out in one piece each, found by
+/// galloping instead of comparing every element.
+#[test]
+fn streaks() {
+    let low: Vec<usize> = (0..1000).collect();
+    let high: Vec<usize> = (1000..2000).collect();
+    let mut comparisons = 0;
+    {
+        let mut merged = kmerge_slices_by(&[&high[..], &low[..]], |a, b| { comparisons += 1; a.cmp(b) });
+        assert_eq!(merged.next_slice(), Some(&low[..]));
+        assert_eq!(merged.next_slice(), Some(&high[..]));
+        assert_eq!(merged.next_slice(), None);
+    }
+    assert!(comparisons < 50);
+}
+
+/// Test that equal elements from a later input wait for the earlier one to
+/// run out of them.
+#[test]
+fn ties() {
+    let merged: Vec<(usize, char)> = kmerge_by(vec![vec![(1, 'a'), (2, 'a')], vec![(1, 'b'), (2, 'b')], vec![(0, 'c'), (1, 'c')]], |a, b| a.0.cmp(&b.0)).collect();
+    assert_eq!(merged, vec![(0, 'c'), (1, 'a'), (1, 'b'), (1, 'c'), (2, 'a'), (2, 'b')]);
+    let (a, b) = ([1, 1, 1], [1, 1]);
+    let mut merged = kmerge_slices_by(&[&b[..], &a[..]], |x: &i32, y: &i32| x.cmp(y));
+    assert_eq!(merged.next_slice().map(|s| s.as_ptr()), Some(b.as_ptr()));
+    assert_eq!(merged.size_hint(), (3, Some(3)));
+}
@@ -0,0 +1,232 @@
+//! Sorting the rows of columnar data by several key columns at once, each
+//! with its own direction and place for nulls.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::Ordering;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use sort::sort;
+
+/// A column of keys that rows can be sorted by.
+///
+/// Rows are identified by their index in the column. Nulls are placed by the
+/// `Lexsort` that the column is part of, so `compare` is only ever called on
+/// two rows that aren't null.
+pub trait Column {
+    /// Returns the number of rows in the column.
+    fn len(&self) -> usize;
+
+    /// Returns true when the column has no rows.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true when row `i` has no value.
+    fn is_null(&self, i: usize) -> bool {
+        let _ = i;
+        false
+    }
+
+    /// Compares rows `i` and `j`, neither of which is null.
+    ///
+    /// Should return std::cmp::Ordering::Greater when row `i` is greater than row `j`.
+    fn compare(&mut self, i: usize, j: usize) -> Ordering;
+}
+
+impl<C: Column + ?Sized> Column for &mut C {
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+    fn is_null(&self, i: usize) -> bool {
+        (**self).is_null(i)
+    }
+    fn compare(&mut self, i: usize, j: usize) -> Ordering {
+        (**self).compare(i, j)
+    }
+}
+
+/// A slice of keys with a total order.
+struct Keys<'a, T: 'a>(&'a [T]);
+
+impl<'a, T: Ord> Column for Keys<'a, T> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn compare(&mut self, i: usize, j: usize) -> Ordering {
+        self.0[i].cmp(&self.0[j])
+    }
+}
+
+/// A slice of keys, some of which are missing.
+struct Nullable<'a, T: 'a>(&'a [Option<T>]);
+
+impl<'a, T: Ord> Column for Nullable<'a, T> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn is_null(&self, i: usize) -> bool {
+        self.0[i].is_none()
+    }
+    fn compare(&mut self, i: usize, j: usize) -> Ordering {
+        self.0[i].cmp(&self.0[j])
+    }
+}
+
+/// A slice of keys compared with a comparator.
+struct KeysBy<'a, T: 'a, C> {
+    keys: &'a [T],
+    c: C,
+}
+
+impl<'a, T, C: FnMut(&T, &T) -> Ordering> Column for KeysBy<'a, T, C> {
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+    fn compare(&mut self, i: usize, j: usize) -> Ordering {
+        (self.c)(&self.keys[i], &self.keys[j])
+    }
+}
+
+/// One of the columns being sorted by, and how.
+struct SortKey<'a> {
+    column: Box<dyn Column + 'a>,
+    descending: bool,
+    nulls_first: bool,
+}
+
+/// Works out the order of the rows of a table kept as separate columns,
+/// sorting by the first column, then by the second where the first is equal,
+/// and so on.
+///
+/// ```
+/// let city = ["Oslo", "Lima", "Oslo", "Lima"];
+/// let age = [Some(30), None, Some(41), Some(25)];
+/// let name = ["Ann", "Bo", "Cy", "Di"];
+/// let perm = timsort::Lexsort::new()
+///     .column(&city)
+///     .nullable(&age).descending()
+///     .column(&name)
+///     .argsort();
+/// assert_eq!(perm, vec![3, 1, 2, 0]);
+/// ```
+///
+/// Unlike numpy's `lexsort`, the first column added is the one compared
+/// first. Each column is ascending with nulls last, unless `descending` or
+/// `nulls_first` is called straight after adding it; nulls stay where they
+/// were put whichever way the column is sorted. The sort is stable, so rows
+/// that are equal in every column keep their order.
+///
+/// The result is a permutation, as from `argsort_by`, which can be passed to
+/// `apply_permutation` to reorder each column, or any other slice that goes
+/// with them.
+pub struct Lexsort<'a> {
+    keys: Vec<SortKey<'a>>,
+    len: usize,
+}
+
+impl<'a> Lexsort<'a> {
+    /// Starts a sort with no columns.
+    pub fn new() -> Lexsort<'a> {
+        Lexsort {
+            keys: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Adds a column to sort by, after the ones already added.
+    ///
+    /// # Panics
+    ///
+    /// If the column isn't the same length as the ones already added.
+    pub fn custom<C: Column + 'a>(mut self, column: C) -> Lexsort<'a> {
+        if self.keys.is_empty() {
+            self.len = column.len();
+        } else {
+            assert_eq!(column.len(), self.len, "column is the wrong length");
+        }
+        self.keys.push(SortKey {
+            column: Box::new(column),
+            descending: false,
+            nulls_first: false,
+        });
+        self
+    }
+
+    /// Adds a column of keys with a total order.
+    pub fn column<T: Ord + 'a>(self, keys: &'a [T]) -> Lexsort<'a> {
+        self.custom(Keys(keys))
+    }
+
+    /// Adds a column of keys, where `None` is a null.
+    pub fn nullable<T: Ord + 'a>(self, keys: &'a [Option<T>]) -> Lexsort<'a> {
+        self.custom(Nullable(keys))
+    }
+
+    /// Adds a column of keys compared with `c`.
+    ///
+    /// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+    pub fn column_by<T: 'a, C: FnMut(&T, &T) -> Ordering + 'a>(self, keys: &'a [T], c: C) -> Lexsort<'a> {
+        self.custom(KeysBy { keys, c })
+    }
+
+    /// Sorts the last column added in descending order.
+    ///
+    /// # Panics
+    ///
+    /// If no columns have been added.
+    pub fn descending(mut self) -> Lexsort<'a> {
+        self.last().descending = true;
+        self
+    }
+
+    /// Puts the nulls in the last column added before everything else.
+    ///
+    /// # Panics
+    ///
+    /// If no columns have been added.
+    pub fn nulls_first(mut self) -> Lexsort<'a> {
+        self.last().nulls_first = true;
+        self
+    }
+
+    fn last(&mut self) -> &mut SortKey<'a> {
+        self.keys.last_mut().expect("no column has been added")
+    }
+
+    /// Returns the indices of the rows in sorted order. With no columns,
+    /// that's an empty list.
+    pub fn argsort(self) -> Vec<usize> {
+        let mut keys = self.keys;
+        let mut perm: Vec<usize> = (0..self.len).collect();
+        sort(&mut perm, |&i, &j| compare_rows(&mut keys, i, j));
+        perm
+    }
+}
+
+impl<'a> Default for Lexsort<'a> {
+    fn default() -> Lexsort<'a> {
+        Lexsort::new()
+    }
+}
+
+/// Compares rows `i` and `j` column by column, stopping at the first column
+/// where they differ.
+fn compare_rows(keys: &mut [SortKey], i: usize, j: usize) -> Ordering {
+    for key in keys {
+        let order = match (key.column.is_null(i), key.column.is_null(j)) {
+            (false, false) => {
+                let order = key.column.compare(i, j);
+                if key.descending { order.reverse() } else { order }
+            }
+            (true, true) => Ordering::Equal,
+            (true, false) => if key.nulls_first { Ordering::Less } else { Ordering::Greater },
+            (false, true) => if key.nulls_first { Ordering::Greater } else { Ordering::Less },
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+    Ordering::Equal
+}
@@ -0,0 +1,87 @@
+//! Sorting the rows of columnar data by several key columns at once.
+
+use core::cmp::{Ordering, Reverse};
+use std::vec::Vec;
+use lexsort::{Column, Lexsort};
+use test_util::pseudo_random;
+
+/// Test that the rows come out in the same order as sorting tuples of the
+/// same keys, in every combination of direction and nulls placement.
+#[test]
+fn matches_tuples() {
+    for &len in &[0, 1, 2, 10, 100, 1000] {
+        let a: Vec<u8> = pseudo_random(len, 5, 1).into_iter().map(|x| x as u8).collect();
+        let b: Vec<Option<u32>> = (0..len).map(|i| {
+            let x = ((i + 3) * 104729) % 7;
+            if x == 0 { None } else { Some(x as u32) }
+        }).collect();
+        let c: Vec<i64> = (0..len).map(|i| (((i + 5) * 31) % 11) as i64).collect();
+        for &descending in &[false, true] {
+            for &nulls_first in &[false, true] {
+                let mut lex = Lexsort::new().column(&a).nullable(&b);
+                if descending {
+                    lex = lex.descending();
+                }
+                if nulls_first {
+                    lex = lex.nulls_first();
+                }
+                let perm = lex.column_by(&c, |x, y| y.cmp(x)).argsort();
+                // `None` sorts first as an `Option`, so flip the null flag
+                // rather than the option when nulls go last.
+                let mut expected: Vec<usize> = (0..len).collect();
+                expected.sort_by_key(|&i| {
+                    let null = b[i].is_none() != nulls_first;
+                    let value = if descending { Err(Reverse(b[i])) } else { Ok(b[i]) };
+                    (a[i], null, value, Reverse(c[i]))
+                });
+                assert_eq!(perm, expected);
+            }
+        }
+    }
+}
+
+/// A column of even numbers, with the odd ones standing for nulls, that
+/// counts its comparisons.
+struct Evens<'a> {
+    values: &'a [u32],
+    compares: usize,
+}
+
+impl<'a> Column for Evens<'a> {
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+    fn is_null(&self, i: usize) -> bool {
+        self.values[i] % 2 == 1
+    }
+    fn compare(&mut self, i: usize, j: usize) -> Ordering {
+        assert!(!self.is_null(i) && !self.is_null(j));
+        self.compares += 1;
+        self.values[i].cmp(&self.values[j])
+    }
+}
+
+/// Test a column supplied by the caller, that later columns are only looked
+/// at to break ties, and that equal rows keep their order.
+#[test]
+fn custom() {
+    let values = [4, 3, 2, 5, 2, 4];
+    let mut evens = Evens { values: &values, compares: 0 };
+    let perm = Lexsort::new().custom(&mut evens).nulls_first().argsort();
+    assert_eq!(perm, [1, 3, 2, 4, 0, 5]);
+    assert!(evens.compares > 0);
+    let unique = [0, 1, 2, 3, 4, 5];
+    let mut never = Evens { values: &[0; 6], compares: 0 };
+    let perm = Lexsort::new().column(&unique).descending().custom(&mut never).argsort();
+    assert_eq!(perm, [5, 4, 3, 2, 1, 0]);
+    assert_eq!(never.compares, 0);
+    let none: [u8; 0] = [];
+    assert!(Lexsort::new().argsort().is_empty());
+    assert!(Lexsort::new().column(&none).argsort().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "wrong length")]
+fn wrong_length() {
+    Lexsort::new().column(&[1, 2, 3]).column(&[1, 2]);
+}
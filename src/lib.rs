@@ -2,18 +2,150 @@
 //! requirements, O(n log n) worst-case comparisons, and O(n) comparisons
 //! on an already-sorted list, smoothly becoming O(n log n) as the sorted
 //! sections (runs) get smaller and smaller.
+//!
+//! The crate is `no_std`. With the `alloc` feature, the merge buffer is
+//! allocated on the heap. Without it, `sort_by_with_buffer` sorts using memory
+//! supplied by the caller instead, and never allocates. The default `std`
+//! feature turns on `alloc`, and adds `par_sort_by`, which sorts on several
+//! threads at once.
+//!
+//! A comparator that isn't a total order can't make any of the sorts touch
+//! memory they shouldn't, or lose or duplicate an element: the list just comes
+//! out in some order that might not be sorted. `sort_by_checked` looks over
+//! each run once it has been merged, and says which two neighbouring elements
+//! it found out of order. Nothing is checked unless it is asked for, in debug
+//! builds or otherwise.
+
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(any(test, feature = "std"))]
+#[macro_use]
+extern crate std;
 
 mod insort;
 mod merge;
 mod gallop;
 mod find_run;
 mod sort;
+mod policy;
+mod observer;
+mod moves;
+mod stats;
+#[cfg(feature = "alloc")]
+mod recorder;
+#[cfg(feature = "alloc")]
+mod key;
+mod scratch;
+#[cfg(feature = "alloc")]
+mod sorter;
+#[cfg(feature = "alloc")]
+mod streaming;
+#[cfg(feature = "alloc")]
+mod kmerge;
+#[cfg(feature = "alloc")]
+mod float;
+#[cfg(feature = "alloc")]
+mod ext;
+#[cfg(feature = "alloc")]
+mod permute;
+#[cfg(feature = "alloc")]
+mod indirect;
+#[cfg(feature = "alloc")]
+mod zip;
+#[cfg(feature = "alloc")]
+mod lexsort;
+#[cfg(feature = "alloc")]
+mod rank;
+#[cfg(feature = "std")]
+mod par;
+mod search;
+mod check;
+#[cfg(test)]
+mod test_util;
 
+#[cfg(feature = "alloc")]
 pub use sort::sort as sort_by;
+#[cfg(feature = "alloc")]
+pub use sort::try_sort as try_sort_by;
+#[cfg(feature = "alloc")]
+pub use sort::sort_checked as sort_by_checked;
+#[cfg(feature = "alloc")]
+pub use sort::try_sort_checked as try_sort_by_checked;
+pub use check::{ComparatorViolation, CheckError};
+#[cfg(feature = "alloc")]
+pub use sort::sort_with_stats as sort_by_with_stats;
+#[cfg(feature = "alloc")]
+pub use sort::sort_with_observer as sort_by_with_observer;
+#[cfg(feature = "alloc")]
+pub use recorder::{SortRecorder, SortEvent};
+pub use stats::SortStats;
+pub use observer::{SortObserver, MergeKind};
+pub use sort::sort_with_buffer as sort_by_with_buffer;
+pub use sort::try_sort_with_buffer as try_sort_by_with_buffer;
+pub use sort::Run;
+pub use policy::{MergePolicy, Timsort, Powersort, ShiversSort, Peeksort};
+pub use search::{lower_bound, lower_bound_by, upper_bound, upper_bound_by, equal_range, equal_range_by};
+#[cfg(feature = "alloc")]
+pub use sorter::TimSorter;
+#[cfg(feature = "alloc")]
+pub use streaming::StreamingSorter;
+#[cfg(feature = "alloc")]
+pub use kmerge::{kmerge_by, kmerge_slices_by, KMerge, KMergeSlices};
+#[cfg(feature = "alloc")]
+pub use merge::merge_adjacent as merge_adjacent_by;
+#[cfg(feature = "alloc")]
+pub use merge::merge_into as merge_into_by;
+#[cfg(feature = "alloc")]
+pub use merge::merge_vecs as merge_vecs_by;
+#[cfg(feature = "std")]
+pub use par::par_sort as par_sort_by;
+#[cfg(feature = "std")]
+pub use par::par_sort_with_threads as par_sort_by_with_threads;
+#[cfg(feature = "std")]
+pub use par::try_par_sort as try_par_sort_by;
+#[cfg(feature = "std")]
+pub use par::try_par_sort_with_threads as try_par_sort_by_with_threads;
+#[cfg(feature = "alloc")]
+pub use key::{sort_by_key, sort_by_key_ref, sort_by_cached_key};
+#[cfg(feature = "alloc")]
+pub use ext::TimSortExt;
+#[cfg(feature = "alloc")]
+pub use indirect::{sort_indirect as sort_by_indirect, try_sort_indirect as try_sort_by_indirect, INDIRECT_SIZE};
+#[cfg(feature = "alloc")]
+pub use permute::{argsort, argsort_by, apply_permutation, invert_permutation};
+#[cfg(feature = "alloc")]
+pub use zip::ZipSort;
+#[cfg(feature = "alloc")]
+pub use lexsort::{Lexsort, Column};
+#[cfg(feature = "alloc")]
+pub use rank::{rank, rank_by, Ranks, Ties};
+#[cfg(feature = "alloc")]
+pub use float::{sort_floats, sort_with_nan_policy, Float, NanPolicy, Unordered};
 
-use std::cmp::Ordering;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use float::nan_last_cmp;
+/// Sorts a list of values that only have a partial order. Values that can't
+/// be compared with themselves, like NaN, go at the end; see
+/// `sort_with_nan_policy`.
+#[cfg(feature = "alloc")]
 pub fn sort<T: PartialOrd>(list: &mut [T]) {
-    sort_by(list, |a, b| {
-        a.partial_cmp(b).unwrap_or(Ordering::Equal)
-    })
+    sort_by(list, nan_last_cmp)
+}
+
+/// Clones the sorted slices `a` and `b` onto the end of `out`, in order.
+/// NaNs go at the end, as they do for `sort`. See `merge_into_by`.
+#[cfg(feature = "alloc")]
+pub fn merge_into<T: PartialOrd + Clone>(a: &[T], b: &[T], out: &mut Vec<T>) {
+    merge_into_by(a, b, out, nan_last_cmp)
+}
+
+/// Merges the sorted vectors `a` and `b` by moving their elements. NaNs go
+/// at the end, as they do for `sort`. See `merge_vecs_by`.
+#[cfg(feature = "alloc")]
+pub fn merge_vecs<T: PartialOrd>(a: Vec<T>, b: Vec<T>) -> Vec<T> {
+    merge_vecs_by(a, b, nan_last_cmp)
 }
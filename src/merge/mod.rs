@@ -1,127 +1,355 @@
-//! The merge algorithm. This one can merge unequal slices, allocating an n/2
-//! sized temporary slice of the same type. Naturally, it can only merge slices
-//! that are themselves already sorted.
+//! The merge algorithm. This one can merge unequal slices, using an n/2 sized
+//! temporary slice of the same type supplied by the caller, or less than that
+//! at the cost of some extra moves. Naturally, it can only merge slices that
+//! are themselves already sorted.
 
 #[cfg(test)]
 mod tests;
 
-use std::cmp::Ordering;
-use std::ptr;
+use core::cmp::Ordering;
+use core::cmp::min;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::slice;
 use gallop::{self, gallop_left, gallop_right};
+use scratch::Scratch;
+use observer::{MergeKind, SortObserver};
+use moves::{Moves, Offset};
+#[cfg(feature = "alloc")]
+use core::convert::Infallible;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use kmerge::kmerge_slices_by;
 
 /// Merge implementation switch.
 ///
 /// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
-pub fn merge<T, C: Fn(&T, &T) -> Ordering>(list: &mut [T], mut first_len: usize, c: C) {
-    let mut second_len: usize;
-    let mut first_off: usize;
+/// If it returns an error, merging stops and the list is left as a permutation
+/// of its original contents. The shorter of the two runs is copied into `tmp`,
+/// unless it doesn't fit, in which case the runs are merged in place.
+///
+/// `min_gallop` is how many times in a row one run has to win before we switch
+/// to galloping. It is adjusted as the merge goes, and should be carried over
+/// from one merge to the next; start it off at `MIN_GALLOP`. Everything the
+/// merge does is reported to `o`, every element it moves to `m`, and how it
+/// was done is returned.
+pub fn merge<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>, S: Scratch<T> + ?Sized, O: SortObserver + ?Sized, M: Moves + ?Sized>(list: &mut [T], first_len: usize, mut c: C, tmp: &mut S, min_gallop: &mut usize, o: &mut O, m: &mut M) -> Result<MergeKind, E> {
+    merge_in(list, 0, first_len, &mut c, tmp, min_gallop, o, m)
+}
+
+/// Merges `list[..mid]` with `list[mid..]`, which must both be sorted already.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+/// The merge is stable, so elements that compare equal keep their order, and
+/// the ones from the first half go first. Whatever is already in place at
+/// either end is found by galloping and left alone, and only the shorter of
+/// what is left of the two halves is copied out.
+#[cfg(feature = "alloc")]
+pub fn merge_adjacent<T, C: FnMut(&T, &T) -> Ordering>(list: &mut [T], mid: usize, mut c: C) {
+    match merge(list, mid, |a, b| Ok::<Ordering, Infallible>(c(a, b)), &mut Vec::new(), &mut { MIN_GALLOP }, &mut (), &mut ()) {
+        Ok(_) => (),
+        Err(e) => match e {},
+    }
+}
+
+/// Clones the elements of `a` and `b`, which must both be sorted already, onto
+/// the end of `out`, in order.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+/// Elements of `a` go before equal elements of `b`. Stretches that come from
+/// just one of them are found by galloping, and cloned all at once.
+#[cfg(feature = "alloc")]
+pub fn merge_into<T: Clone, C: FnMut(&T, &T) -> Ordering>(a: &[T], b: &[T], out: &mut Vec<T>, c: C) {
+    out.reserve(a.len() + b.len());
+    let mut merged = kmerge_slices_by(&[a, b], c);
+    while let Some(streak) = merged.next_slice() {
+        out.extend_from_slice(streak);
+    }
+}
+
+/// Merges `a` and `b`, which must both be sorted already, without cloning
+/// anything.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+/// Elements of `a` go before equal elements of `b`. The elements of `b` are
+/// moved onto the end of `a`, which is what gets returned, and the buffer
+/// that `b` leaves behind is used as the scratch space for the merge.
+#[cfg(feature = "alloc")]
+pub fn merge_vecs<T, C: FnMut(&T, &T) -> Ordering>(mut a: Vec<T>, mut b: Vec<T>, mut c: C) -> Vec<T> {
+    let mid = a.len();
+    a.append(&mut b);
+    match merge(&mut a, mid, |x, y| Ok::<Ordering, Infallible>(c(x, y)), &mut b, &mut { MIN_GALLOP }, &mut (), &mut ()) {
+        Ok(_) => a,
+        Err(e) => match e {},
+    }
+}
+
+/// Implementation of `merge`. The comparator is always passed by reference,
+/// so that `merge_rotate` can call back into this without a new type each time.
+/// For the same reason, the moves are reported to `m` as they would be for
+/// `list[at..]` in the list it was given, rather than through an `Offset`.
+#[allow(clippy::too_many_arguments)]
+fn merge_in<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>, S: Scratch<T> + ?Sized, O: SortObserver + ?Sized, M: Moves + ?Sized>(list: &mut [T], at: usize, mut first_len: usize, c: &mut C, tmp: &mut S, min_gallop: &mut usize, o: &mut O, m: &mut M) -> Result<MergeKind, E> {
+    let second_len: usize;
+    let first_off: usize;
     if first_len == 0 {
-        return;
+        return Ok(MergeKind::InOrder);
     }
     unsafe {
         let (first, second) = list.split_at_mut(first_len);
-        second_len = gallop_left(first.get_unchecked(first_len - 1), second, gallop::Mode::Reverse, &c);
+        second_len = gallop_left(first.get_unchecked(first_len - 1), second, gallop::Mode::Reverse, &mut *c)?;
         if second_len == 0 {
-            return;
+            return Ok(MergeKind::InOrder);
         }
-        first_off = gallop_right(second.get_unchecked(0), first, gallop::Mode::Forward, &c);
+        first_off = gallop_right(second.get_unchecked(0), first, gallop::Mode::Forward, &mut *c)?;
         first_len -= first_off;
         if first_len == 0 {
-            return;
+            return Ok(MergeKind::InOrder);
         }
     }
     let nlist = list.split_at_mut(first_off).1.split_at_mut(first_len + second_len).0;
-    if first_len > second_len {
-        merge_hi(nlist, first_len, second_len, c);
+    let tmp_len = min(first_len, second_len);
+    if tmp_len > tmp.max_len() {
+        merge_rotate(nlist, at + first_off, first_len, second_len, c, tmp, min_gallop, o, m)
     } else {
-        merge_lo(nlist, first_len, c);
+        o.scratch(tmp_len);
+        let m = &mut Offset::new(m, at + first_off);
+        if first_len > second_len {
+            merge_hi(nlist, first_len, second_len, c, tmp.get(tmp_len), min_gallop, o, m)?;
+            Ok(MergeKind::Hi)
+        } else {
+            merge_lo(nlist, first_len, c, tmp.get(tmp_len), min_gallop, o, m)?;
+            Ok(MergeKind::Lo)
+        }
     }
 }
 
-/// The number of times any one run can win before we try galloping.
+/// Finds how many of the first `pos` elements of `first` and `second` merged
+/// together come from `first`. Ties go to `first`, to keep the merge stable.
+///
+/// The answer is the last `i` where `first[i - 1]` goes before
+/// `second[pos - i]`, and it is found with a binary search.
+#[cfg(feature = "alloc")]
+pub fn co_rank<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>>(pos: usize, first: &[T], second: &[T], mut c: C) -> Result<usize, E> {
+    let mut lo = pos.saturating_sub(second.len());
+    let mut hi = min(pos, first.len());
+    while lo < hi {
+        let i = lo + (hi - lo + 1) / 2;
+        if c(&first[i - 1], &second[pos - i])? == Ordering::Greater {
+            hi = i - 1;
+        } else {
+            lo = i;
+        }
+    }
+    Ok(lo)
+}
+
+/// Merges two sorted slices that aren't next to each other, as if `second`
+/// came right after `first`. Afterwards, `first` holds the first `first.len()`
+/// elements of the result, and `second` holds the rest.
+///
+/// `co_rank` finds how many elements of `first` stay there. The ones that
+/// don't swap places with just as many from the start of `second`, which
+/// leaves two ordinary merges, one in each slice.
+#[cfg(feature = "alloc")]
+pub fn merge_apart<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>, S: Scratch<T> + ?Sized>(first: &mut [T], second: &mut [T], mut c: C, tmp: &mut S) -> Result<(), E> {
+    let len = first.len();
+    let stay = co_rank(len, first, second, &mut c)?;
+    first[stay..].swap_with_slice(&mut second[..len - stay]);
+    let mut min_gallop = MIN_GALLOP;
+    merge(first, stay, &mut c, tmp, &mut min_gallop, &mut (), &mut ())?;
+    merge(second, len - stay, &mut c, tmp, &mut min_gallop, &mut (), &mut ())?;
+    Ok(())
+}
+
+/// Merge implementation used when neither run fits in the scratch space.
+///
+/// The longer run is cut in half, and the other run is cut where the middle
+/// element of the longer run would go. The two middle pieces then swap places
+/// with a rotation, which leaves two smaller merges, each of which is either
+/// small enough to go through `tmp` or gets cut in half again. Nothing is
+/// moved while the comparator is being called, so the list can't be left with
+/// anything missing if it fails.
+///
+/// `merge_in` has already galloped to where the last element of the first run
+/// and the first element of the second run go, so when either run is a single
+/// element, it is rotated straight into place without asking the comparator
+/// again. Cutting a single element in half would leave a piece as big as the
+/// whole, and a comparator that changes its mind could keep that going
+/// forever.
+#[allow(clippy::too_many_arguments)]
+fn merge_rotate<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>, S: Scratch<T> + ?Sized, O: SortObserver + ?Sized, M: Moves + ?Sized>(list: &mut [T], at: usize, first_len: usize, second_len: usize, c: &mut C, tmp: &mut S, min_gallop: &mut usize, o: &mut O, m: &mut M) -> Result<MergeKind, E> {
+    let len = list.len();
+    if first_len == 1 {
+        list.rotate_left(1);
+        m.rotate(at, at + 1, at + len);
+        o.moved(len);
+        return Ok(MergeKind::InPlace);
+    }
+    if second_len == 1 {
+        list.rotate_right(1);
+        m.rotate(at, at + len - 1, at + len);
+        o.moved(len);
+        return Ok(MergeKind::InPlace);
+    }
+    // Both runs have at least two elements, so both cuts are inside the
+    // longer run, and each of the two merges below is smaller than this one.
+    let (first_cut, second_cut) = {
+        let (first, second) = list.split_at(first_len);
+        if first_len >= second_len {
+            let first_cut = first_len / 2;
+            (first_cut, gallop_left(&first[first_cut], second, gallop::Mode::Forward, &mut *c)?)
+        } else {
+            let second_cut = second_len / 2;
+            (gallop_right(&second[second_cut], first, gallop::Mode::Forward, &mut *c)?, second_cut)
+        }
+    };
+    list[first_cut..(first_len + second_cut)].rotate_left(first_len - first_cut);
+    m.rotate(at + first_cut, at + first_len, at + first_len + second_cut);
+    o.moved(first_len - first_cut + second_cut);
+    let (lo, hi) = list.split_at_mut(first_cut + second_cut);
+    merge_in(lo, at, first_cut, c, tmp, min_gallop, o, m)?;
+    merge_in(hi, at + first_cut + second_cut, first_len - first_cut, c, tmp, min_gallop, o, m)?;
+    Ok(MergeKind::InPlace)
+}
+
+/// The number of times any one run has to win before we try galloping, at the
+/// start of a sort. After that, `min_gallop` drifts up or down depending on
+/// how well galloping works out. It is also how many elements a gallop has to
+/// skip over for us to stay in galloping mode.
 /// Change this during testing.
-const MIN_GALLOP: usize = 7;
+pub const MIN_GALLOP: usize = 7;
 
 /// Merge implementation used when the first run is smaller than the second.
-pub fn merge_lo<T, C: Fn(&T, &T) -> Ordering>(list: &mut [T], first_len: usize, c: C) {
+///
+/// `tmp` must have room for at least `first_len` elements.
+#[allow(clippy::too_many_arguments)]
+pub fn merge_lo<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>, O: SortObserver + ?Sized, M: Moves + ?Sized>(list: &mut [T], first_len: usize, c: C, tmp: &mut [MaybeUninit<T>], min_gallop: &mut usize, o: &mut O, m: &mut M) -> Result<(), E> {
+    assert!(tmp.len() >= first_len);
+    o.merge_lo(first_len, list.len() - first_len);
     unsafe {
-        let mut state = MergeLo::new(list, first_len, c);
-        state.merge();
+        let mut state = MergeLo::new(list, first_len, c, tmp, min_gallop, o, m);
+        state.merge()
     }
 }
 
 /// Implementation of `merge_lo`. We need to have an object in order to
 /// implement panic safety.
-struct MergeLo<'a, T: 'a, C: Fn(&T, &T) -> Ordering> {
+struct MergeLo<'a, T: 'a, E, C: FnMut(&T, &T) -> Result<Ordering, E>, O: 'a + SortObserver + ?Sized, M: 'a + Moves + ?Sized> {
     list_len: usize,
     first_pos: usize,
     first_len: usize,
     second_pos: usize,
     dest_pos: usize,
     list: &'a mut [T],
-    tmp: Vec<T>,
+    tmp: &'a mut [T],
+    min_gallop: &'a mut usize,
+    o: &'a mut O,
+    m: &'a mut M,
     c: C,
+    _e: PhantomData<E>,
 }
-impl<'a, T: 'a, C: Fn(&T, &T) -> Ordering> MergeLo<'a, T, C> {
+impl<'a, T: 'a, E, C: FnMut(&T, &T) -> Result<Ordering, E>, O: 'a + SortObserver + ?Sized, M: 'a + Moves + ?Sized> MergeLo<'a, T, E, C, O, M> {
     /// Constructor for a lower merge.
-    unsafe fn new(list: &'a mut [T], first_len: usize, c: C) -> Self {
-        let mut ret_val = MergeLo{
+    unsafe fn new(list: &'a mut [T], first_len: usize, c: C, tmp: &'a mut [MaybeUninit<T>], min_gallop: &'a mut usize, o: &'a mut O, m: &'a mut M) -> Self {
+        let ret_val = MergeLo{
             list_len:   list.len(),
             first_pos:  0,
-            first_len:  first_len,
+            first_len,
             second_pos: first_len,
             dest_pos:   0,
-            list:       list,
-            tmp:        Vec::with_capacity(first_len),
-            c:          c,
+            list,
+            tmp:        slice::from_raw_parts_mut(tmp.as_mut_ptr() as *mut T, first_len),
+            min_gallop,
+            o,
+            m,
+            c,
+            _e:         PhantomData,
         };
         // First, move the smallest run into temporary storage, leaving the
         // original contents uninitialized.
-        ret_val.tmp.set_len(first_len);
         for i in 0..first_len {
             ptr::copy_nonoverlapping(ret_val.list.get_unchecked(i), ret_val.tmp.get_unchecked_mut(i), 1);
         }
+        ret_val.m.stash(0, first_len);
+        ret_val.o.moved(first_len);
         ret_val
     }
     /// Perform the one-by-one comparison and insertion.
-    unsafe fn merge(&mut self) {
-        let c = &self.c;
+    unsafe fn merge(&mut self) -> Result<(), E> {
+        let c = &mut self.c;
+        let min_gallop = &mut *self.min_gallop;
+        let o = &mut *self.o;
+        let m = &mut *self.m;
         let mut first_count  = 0;
         let mut second_count = 0;
+        let mut galloping = false;
         while self.second_pos > self.dest_pos && self.second_pos < self.list_len {
             debug_assert!(self.first_pos + (self.second_pos - self.first_len) == self.dest_pos);
-            if (second_count | first_count) < MIN_GALLOP {
+            if !galloping {
                 // One-at-a-time mode.
-                if c(self.tmp.get_unchecked(self.first_pos), self.list.get_unchecked(self.second_pos)) == Ordering::Greater {
+                if c(self.tmp.get_unchecked(self.first_pos), self.list.get_unchecked(self.second_pos))? == Ordering::Greater {
                     ptr::copy_nonoverlapping(self.list.get_unchecked(self.second_pos), self.list.get_unchecked_mut(self.dest_pos), 1);
+                    m.shift(self.second_pos, self.dest_pos, 1);
                     self.second_pos += 1;
                     second_count += 1;
                     first_count = 0;
                 } else {
                     ptr::copy_nonoverlapping(self.tmp.get_unchecked(self.first_pos), self.list.get_unchecked_mut(self.dest_pos), 1);
+                    m.unstash(self.first_pos, self.dest_pos, 1);
                     self.first_pos += 1;
                     first_count += 1;
                     second_count = 0;
                 }
                 self.dest_pos += 1;
+                o.moved(1);
+                if first_count >= *min_gallop || second_count >= *min_gallop {
+                    // Cancelled out by the first pass through galloping mode.
+                    *min_gallop += 1;
+                    galloping = true;
+                    o.gallop_enter();
+                }
             } else {
-                // Galloping mode.
-                second_count = gallop_left(self.tmp.get_unchecked(self.first_pos), self.list.split_at(self.second_pos).1, gallop::Mode::Forward, c);
+                // Galloping mode. Every pass through here makes it easier to
+                // get back in later.
+                if *min_gallop > 1 {
+                    *min_gallop -= 1;
+                }
+                second_count = gallop_left(self.tmp.get_unchecked(self.first_pos), self.list.split_at(self.second_pos).1, gallop::Mode::Forward, &mut *c)?;
                 ptr::copy(self.list.get_unchecked(self.second_pos), self.list.get_unchecked_mut(self.dest_pos), second_count);
+                m.shift(self.second_pos, self.dest_pos, second_count);
                 self.dest_pos   += second_count;
                 self.second_pos += second_count;
+                o.galloped(second_count);
+                o.moved(second_count);
                 debug_assert!(self.first_pos + (self.second_pos - self.first_len) == self.dest_pos);
+                first_count = 0;
                 if self.second_pos > self.dest_pos && self.second_pos < self.list_len {
-                    first_count = gallop_right(self.list.get_unchecked(self.second_pos), self.tmp.split_at(self.first_pos).1, gallop::Mode::Forward, c);
+                    first_count = gallop_right(self.list.get_unchecked(self.second_pos), self.tmp.split_at(self.first_pos).1, gallop::Mode::Forward, &mut *c)?;
                     ptr::copy_nonoverlapping(self.tmp.get_unchecked(self.first_pos), self.list.get_unchecked_mut(self.dest_pos), first_count);
+                    m.unstash(self.first_pos, self.dest_pos, first_count);
                     self.dest_pos  += first_count;
                     self.first_pos += first_count;
+                    o.galloped(first_count);
+                    o.moved(first_count);
+                }
+                if first_count < MIN_GALLOP && second_count < MIN_GALLOP {
+                    // Galloping isn't paying off, so go back to one at a time,
+                    // and make it harder to get back in.
+                    *min_gallop += 1;
+                    galloping = false;
+                    first_count = 0;
+                    second_count = 0;
+                    o.gallop_exit();
                 }
             }
         }
+        Ok(())
     }
 }
-impl<'a, T: 'a, C: Fn(&T, &T) -> Ordering> Drop for MergeLo<'a, T, C> {
+impl<'a, T: 'a, E, C: FnMut(&T, &T) -> Result<Ordering, E>, O: 'a + SortObserver + ?Sized, M: 'a + Moves + ?Sized> Drop for MergeLo<'a, T, E, C, O, M> {
     /// Copy all remaining items in the temporary storage into the list.
     /// If the comparator panics, the result will not be sorted, but will still
     /// contain no duplicates or uninitialized spots.
@@ -133,84 +361,138 @@ impl<'a, T: 'a, C: Fn(&T, &T) -> Ordering> Drop for MergeLo<'a, T, C> {
             // function is safe.
             if self.first_pos < self.first_len {
                 ptr::copy_nonoverlapping(self.tmp.get_unchecked(self.first_pos), self.list.get_unchecked_mut(self.dest_pos), self.first_len - self.first_pos);
+                self.m.unstash(self.first_pos, self.dest_pos, self.first_len - self.first_pos);
+                self.o.moved(self.first_len - self.first_pos);
             }
             // The temporary storage is now full of nothing but uninitialized.
-            // We want to deallocate the space, but not call the destructors.
-            self.tmp.set_len(0);
+            // It belongs to the caller, who must not call the destructors.
         }
     }
 }
 
 /// Merge implementation used when the first run is larger than the second.
-pub fn merge_hi<T, C: Fn(&T, &T) -> Ordering>(list: &mut [T], first_len: usize, second_len: usize, c: C) {
+///
+/// `tmp` must have room for at least `second_len` elements.
+#[allow(clippy::too_many_arguments)]
+pub fn merge_hi<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>, O: SortObserver + ?Sized, M: Moves + ?Sized>(list: &mut [T], first_len: usize, second_len: usize, c: C, tmp: &mut [MaybeUninit<T>], min_gallop: &mut usize, o: &mut O, m: &mut M) -> Result<(), E> {
+    assert!(tmp.len() >= second_len);
+    o.merge_hi(first_len, second_len);
     unsafe {
-        let mut state = MergeHi::new(list, first_len, second_len, c);
-        state.merge();
+        let mut state = MergeHi::new(list, first_len, second_len, c, tmp, min_gallop, o, m);
+        state.merge()
     }
 }
 
 /// Implementation of `merge_hi`. We need to have an object in order to
 /// implement panic safety.
-struct MergeHi<'a, T: 'a, C: Fn(&T, &T) -> Ordering> {
+struct MergeHi<'a, T: 'a, E, C: FnMut(&T, &T) -> Result<Ordering, E>, O: 'a + SortObserver + ?Sized, M: 'a + Moves + ?Sized> {
     first_pos: isize,
     second_pos: isize,
     dest_pos: isize,
     list: &'a mut [T],
-    tmp: Vec<T>,
-    c: C
+    tmp: &'a mut [T],
+    min_gallop: &'a mut usize,
+    o: &'a mut O,
+    m: &'a mut M,
+    c: C,
+    _e: PhantomData<E>,
 }
 
-impl<'a, T: 'a, C: Fn(&T, &T) -> Ordering> MergeHi<'a, T, C> {
+impl<'a, T: 'a, E, C: FnMut(&T, &T) -> Result<Ordering, E>, O: 'a + SortObserver + ?Sized, M: 'a + Moves + ?Sized> MergeHi<'a, T, E, C, O, M> {
     /// Constructor for a higher merge.
-    unsafe fn new(list: &'a mut [T], first_len: usize, second_len: usize, c: C) -> Self {
-        let mut ret_val = MergeHi{
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn new(list: &'a mut [T], first_len: usize, second_len: usize, c: C, tmp: &'a mut [MaybeUninit<T>], min_gallop: &'a mut usize, o: &'a mut O, m: &'a mut M) -> Self {
+        let ret_val = MergeHi{
             first_pos:  first_len as isize - 1,
             second_pos: second_len as isize - 1,
             dest_pos:   list.len() as isize - 1,
-            list:       list,
-            tmp:        Vec::with_capacity(second_len),
-            c:          c
+            list,
+            tmp:        slice::from_raw_parts_mut(tmp.as_mut_ptr() as *mut T, second_len),
+            min_gallop,
+            o,
+            m,
+            c,
+            _e:         PhantomData,
         };
         // First, move the smallest run into temporary storage, leaving the
         // original contents uninitialized.
-        ret_val.tmp.set_len(second_len);
         for i in 0..second_len {
             ptr::copy_nonoverlapping(ret_val.list.get_unchecked(i + first_len), ret_val.tmp.get_unchecked_mut(i), 1);
         }
+        ret_val.m.stash(first_len, second_len);
+        ret_val.o.moved(second_len);
         ret_val
     }
     /// Perform the one-by-one comparison and insertion.
-    unsafe fn merge(&mut self) {
-        let c = &self.c;
+    unsafe fn merge(&mut self) -> Result<(), E> {
+        let c = &mut self.c;
+        let min_gallop = &mut *self.min_gallop;
+        let o = &mut *self.o;
+        let m = &mut *self.m;
         let mut first_count: usize  = 0;
         let mut second_count: usize = 0;
+        let mut galloping = false;
         while self.first_pos < self.dest_pos && self.first_pos >= 0 {
             debug_assert!(self.first_pos + self.second_pos + 1 == self.dest_pos);
-            if (second_count | first_count) < MIN_GALLOP {
+            if !galloping {
                 // One-at-a-time mode.
-                if c(self.tmp.get_unchecked(self.second_pos as usize), self.list.get_unchecked(self.first_pos as usize)) != Ordering::Less {
+                if c(self.tmp.get_unchecked(self.second_pos as usize), self.list.get_unchecked(self.first_pos as usize))? != Ordering::Less {
                     ptr::copy_nonoverlapping(self.tmp.get_unchecked(self.second_pos as usize), self.list.get_unchecked_mut(self.dest_pos as usize), 1);
+                    m.unstash(self.second_pos as usize, self.dest_pos as usize, 1);
                     self.second_pos -= 1;
+                    second_count += 1;
+                    first_count = 0;
                 } else {
                     ptr::copy_nonoverlapping(self.list.get_unchecked(self.first_pos as usize), self.list.get_unchecked_mut(self.dest_pos as usize), 1);
+                    m.shift(self.first_pos as usize, self.dest_pos as usize, 1);
                     self.first_pos -= 1;
+                    first_count += 1;
+                    second_count = 0;
                 }
                 self.dest_pos -= 1;
+                o.moved(1);
+                if first_count >= *min_gallop || second_count >= *min_gallop {
+                    // Cancelled out by the first pass through galloping mode.
+                    *min_gallop += 1;
+                    galloping = true;
+                    o.gallop_enter();
+                }
             } else {
-                // Galloping mode.
-                first_count = self.first_pos as usize + 1 - gallop_right(self.tmp.get_unchecked(self.second_pos as usize), self.list.split_at(self.first_pos as usize + 1).0, gallop::Mode::Reverse, c);
+                // Galloping mode. Every pass through here makes it easier to
+                // get back in later.
+                if *min_gallop > 1 {
+                    *min_gallop -= 1;
+                }
+                first_count = self.first_pos as usize + 1 - gallop_right(self.tmp.get_unchecked(self.second_pos as usize), self.list.split_at(self.first_pos as usize + 1).0, gallop::Mode::Reverse, &mut *c)?;
                 copy_backwards(self.list.get_unchecked(self.first_pos as usize), self.list.get_unchecked_mut(self.dest_pos as usize), first_count);
+                m.shift(self.first_pos as usize + 1 - first_count, self.dest_pos as usize + 1 - first_count, first_count);
                 self.dest_pos  -= first_count as isize;
                 self.first_pos -= first_count as isize;
+                o.galloped(first_count);
+                o.moved(first_count);
                 debug_assert!(self.first_pos + self.second_pos + 1 == self.dest_pos);
+                second_count = 0;
                 if self.first_pos < self.dest_pos && self.first_pos >= 0 {
-                    second_count = self.second_pos as usize + 1 - gallop_left(self.list.get_unchecked(self.first_pos as usize), self.tmp.split_at(self.second_pos as usize + 1).0, gallop::Mode::Reverse, c);
+                    second_count = self.second_pos as usize + 1 - gallop_left(self.list.get_unchecked(self.first_pos as usize), self.tmp.split_at(self.second_pos as usize + 1).0, gallop::Mode::Reverse, &mut *c)?;
                     copy_nonoverlapping_backwards(self.tmp.get_unchecked(self.second_pos as usize), self.list.get_unchecked_mut(self.dest_pos as usize), second_count);
+                    m.unstash(self.second_pos as usize + 1 - second_count, self.dest_pos as usize + 1 - second_count, second_count);
                     self.dest_pos   -= second_count as isize;
                     self.second_pos -= second_count as isize;
+                    o.galloped(second_count);
+                    o.moved(second_count);
+                }
+                if first_count < MIN_GALLOP && second_count < MIN_GALLOP {
+                    // Galloping isn't paying off, so go back to one at a time,
+                    // and make it harder to get back in.
+                    *min_gallop += 1;
+                    galloping = false;
+                    first_count = 0;
+                    second_count = 0;
+                    o.gallop_exit();
                 }
             }
         }
+        Ok(())
     }
 }
 
@@ -226,7 +508,7 @@ unsafe fn copy_nonoverlapping_backwards<T>(src: *const T, dest: *mut T, size: us
     ptr::copy_nonoverlapping(src.offset(-(size as isize - 1)), dest.offset(-(size as isize - 1)), size)
 }
 
-impl<'a, T: 'a, C: Fn(&T, &T) -> Ordering> Drop for MergeHi<'a, T, C> {
+impl<'a, T: 'a, E, C: FnMut(&T, &T) -> Result<Ordering, E>, O: 'a + SortObserver + ?Sized, M: 'a + Moves + ?Sized> Drop for MergeHi<'a, T, E, C, O, M> {
     /// Copy all remaining items in the temporary storage into the list.
     /// If the comparator panics, the result will not be sorted, but will still
     /// contain no duplicates or uninitialized spots.
@@ -238,11 +520,12 @@ impl<'a, T: 'a, C: Fn(&T, &T) -> Ordering> Drop for MergeHi<'a, T, C> {
             // function is safe.
             if self.second_pos >= 0 {
                 copy_nonoverlapping_backwards(self.tmp.get_unchecked(self.second_pos as usize), self.list.get_unchecked_mut(self.dest_pos as usize), self.second_pos as usize + 1);
+                self.m.unstash(0, self.dest_pos as usize - self.second_pos as usize, self.second_pos as usize + 1);
+                self.o.moved(self.second_pos as usize + 1);
             }
 
             // The temporary storage is now full of nothing but uninitialized.
-            // We want to deallocate the space, but not call the destructors.
-            self.tmp.set_len(0);
+            // It belongs to the caller, who must not call the destructors.
         }
     }
 }
@@ -2,14 +2,16 @@
 //! sized temporary slice of the same type. Naturally, it can only merge slices
 //! that are themselves already sorted.
 
+use std::vec::Vec;
 use merge;
+use std::cmp::Ordering;
 
 /// Test mergeing two empty slices.
 #[test]
 fn empty() {
     let mut list: Vec<u32> = vec![];
     merge(&mut list, 0);
-    assert!(list.len() == 0);
+    assert!(list.is_empty());
 }
 
 /// Test merging two equal-sized single-element vectors that are already sorted.
@@ -87,7 +89,7 @@ fn lo_panic() {
         let list2p: *mut Vec<usize> = &mut list;
         let list2: &mut Vec<usize> = &mut *list2p;
         let _ = thread::spawn(move || {
-            merge::merge(list2, 3, |_, _| { panic!("Expected panic: this is normal") });
+            merge::merge(list2, 3, |_, _| -> Result<Ordering, ()> { panic!("Expected panic: this is normal") }, &mut Vec::new(), &mut { merge::MIN_GALLOP }, &mut (), &mut ()).unwrap();
         }).join().err().unwrap();
     }
     assert!(list[0] == 1);
@@ -106,7 +108,7 @@ fn hi_panic() {
         let list2p: *mut Vec<usize> = &mut list;
         let list2: &mut Vec<usize> = &mut *list2p;
         let _ = thread::spawn(move || {
-            merge::merge(list2, 2, |_, _| { panic!("Expected panic: this is normal") });
+            merge::merge(list2, 2, |_, _| -> Result<Ordering, ()> { panic!("Expected panic: this is normal") }, &mut Vec::new(), &mut { merge::MIN_GALLOP }, &mut (), &mut ()).unwrap();
         }).join().err().unwrap();
     }
     assert!(list[0] == 1);
@@ -116,6 +118,72 @@ fn hi_panic() {
     assert!(list[4] == 5);
 }
 
+/// Test that an error from the comparator leaves the list intact when the first run is longest
+#[test]
+fn lo_error() {
+    let mut list = vec![1, 2, 3, 4, 5];
+    let result = merge::merge(&mut list, 3, |_, _| Err("expected error"), &mut Vec::new(), &mut { merge::MIN_GALLOP }, &mut (), &mut ());
+    assert_eq!(result, Err("expected error"));
+    assert!(list[0] == 1);
+    assert!(list[1] == 2);
+    assert!(list[2] == 3);
+    assert!(list[3] == 4);
+    assert!(list[4] == 5);
+}
+
+/// Test that an error from the comparator leaves the list intact when the second run is longest
+#[test]
+fn hi_error() {
+    let mut list = vec![1, 2, 3, 4, 5];
+    let result = merge::merge(&mut list, 2, |_, _| Err("expected error"), &mut Vec::new(), &mut { merge::MIN_GALLOP }, &mut (), &mut ());
+    assert_eq!(result, Err("expected error"));
+    assert!(list[0] == 1);
+    assert!(list[1] == 2);
+    assert!(list[2] == 3);
+    assert!(list[3] == 4);
+    assert!(list[4] == 5);
+}
+
+/// Test that an error part way through galloping leaves every element in the list
+#[test]
+fn gallop_error() {
+    use std::cell::Cell;
+    let original = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20];
+    for fail_at in 0..40 {
+        for &first_len in &[10, 21] {
+            let mut list = original.clone();
+            let calls = Cell::new(0);
+            let _ = merge::merge(&mut list, first_len, |a: &usize, b: &usize| {
+                calls.set(calls.get() + 1);
+                if calls.get() > fail_at { Err(()) } else { Ok(a.cmp(b)) }
+            }, &mut Vec::new(), &mut 1, &mut (), &mut ());
+            let mut expected = original.clone();
+            expected.sort();
+            list.sort();
+            assert_eq!(list, expected);
+        }
+    }
+}
+
+/// Test merging in place, with no scratch space at all, keeps equal items in order.
+#[test]
+fn rotate_stable() {
+    use std::mem::MaybeUninit;
+    for first_len in 0..40 {
+        let mut list: Vec<(usize, usize)> = (0..40).map(|i| (i % 7, i)).collect();
+        list[..first_len].sort();
+        list[first_len..].sort();
+        let tmp: &mut [MaybeUninit<(usize, usize)>] = &mut [];
+        merge::merge(&mut list, first_len, |a, b| Ok::<_, ()>(a.0.cmp(&b.0)), tmp, &mut { merge::MIN_GALLOP }, &mut (), &mut ()).unwrap();
+        for i in 1..list.len() {
+            assert!(list[i - 1].0 <= list[i].0);
+            if list[i - 1].0 == list[i].0 {
+                assert!(list[i - 1].1 < list[i].1);
+            }
+        }
+    }
+}
+
 /// Test that the drop() is never run while sorting.
 
 #[test]
@@ -128,7 +196,7 @@ fn lo_nodrop() {
         }
     }
     let mut list = vec![ExplodeOnDrop(3), ExplodeOnDrop(7), ExplodeOnDrop(2)];
-    merge::merge(&mut list, 2, |a, b| {a.0.cmp(&b.0) });
+    merge::merge(&mut list, 2, |a, b| Ok::<_, ()>(a.0.cmp(&b.0)), &mut Vec::new(), &mut { merge::MIN_GALLOP }, &mut (), &mut ()).unwrap();
     assert!(list[0].0 == 2);
     assert!(list[1].0 == 3);
     assert!(list[2].0 == 7);
@@ -145,7 +213,7 @@ fn hi_nodrop() {
         }
     }
     let mut list = vec![ExplodeOnDrop(3), ExplodeOnDrop(2), ExplodeOnDrop(7)];
-    merge::merge(&mut list, 1, |a, b| {a.0.cmp(&b.0) });
+    merge::merge(&mut list, 1, |a, b| Ok::<_, ()>(a.0.cmp(&b.0)), &mut Vec::new(), &mut { merge::MIN_GALLOP }, &mut (), &mut ()).unwrap();
     assert!(list[0].0 == 2);
     assert!(list[1].0 == 3);
     assert!(list[2].0 == 7);
@@ -230,8 +298,110 @@ fn hi_gallop_stress() {
     assert!(list[30] == 30);
 }
 
+/// Make sure interleaved runs, where galloping never pays off, make it harder to start.
+#[test]
+fn min_gallop_rises() {
+    for &(first_len, len) in &[(50, 101), (51, 101)] {
+        let mut list: Vec<usize> = (0..first_len).map(|i| i * 2).chain((0..(len - first_len)).map(|i| i * 2 + 1)).collect();
+        let mut min_gallop = 1;
+        merge::merge(&mut list, first_len, |a, b| Ok::<_, ()>(a.cmp(b)), &mut Vec::new(), &mut min_gallop, &mut (), &mut ()).unwrap();
+        assert!(min_gallop > 1);
+        for i in 1..list.len() {
+            assert!(list[i - 1] <= list[i]);
+        }
+    }
+}
+
+/// Make sure runs that interleave in big blocks make it easier to start galloping.
+#[test]
+fn min_gallop_falls() {
+    for &(first_len, len) in &[(200, 500), (300, 500)] {
+        let mut list: Vec<usize> = (0..first_len).map(|i| (i / 50) * 100 + i % 50).chain((0..(len - first_len)).map(|i| (i / 50) * 100 + 50 + i % 50)).collect();
+        let mut min_gallop = merge::MIN_GALLOP;
+        merge::merge(&mut list, first_len, |a, b| Ok::<_, ()>(a.cmp(b)), &mut Vec::new(), &mut min_gallop, &mut (), &mut ()).unwrap();
+        assert!(min_gallop < merge::MIN_GALLOP);
+        for i in 1..list.len() {
+            assert!(list[i - 1] <= list[i]);
+        }
+    }
+}
+
 /// Merge convenience used for tests.
 pub fn merge<T: Ord>(list: &mut [T], first_len: usize) {
-    merge::merge(list, first_len, |a, b| a.cmp(b) );
+    merge::merge(list, first_len, |a, b| Ok::<_, ()>(a.cmp(b)), &mut Vec::new(), &mut { merge::MIN_GALLOP }, &mut (), &mut ()).unwrap();
+}
+
+
+/// Test the public merges against each other, with ties between the halves.
+#[test]
+fn public_merges() {
+    let a: Vec<(usize, char)> = (0..300).map(|i| (i / 3 * 2, 'a')).collect();
+    let b: Vec<(usize, char)> = (0..100).map(|i| (i + 50, 'b')).collect();
+    let mut expected = a.clone();
+    expected.extend(b.iter().cloned());
+    expected.sort_by_key(|x| x.0);
+    let mut list = a.clone();
+    list.extend(b.iter().cloned());
+    merge::merge_adjacent(&mut list, a.len(), |x, y| x.0.cmp(&y.0));
+    assert_eq!(list, expected);
+    let mut out = vec![(0, 'x')];
+    merge::merge_into(&a, &b, &mut out, |x, y| x.0.cmp(&y.0));
+    assert_eq!(out[0], (0, 'x'));
+    assert_eq!(out[1..], expected[..]);
+    assert_eq!(merge::merge_vecs(a, b, |x, y| x.0.cmp(&y.0)), expected);
 }
 
+/// Test that `merge_vecs` works on elements that can't be cloned, and that
+/// either side can be empty.
+#[test]
+fn merge_vecs_moves() {
+    struct NoClone(usize);
+    let a: Vec<NoClone> = (0..10).map(|i| NoClone(i * 2)).collect();
+    let b: Vec<NoClone> = (0..10).map(|i| NoClone(i * 2 + 1)).collect();
+    let merged = merge::merge_vecs(a, b, |x, y| x.0.cmp(&y.0));
+    assert!(merged.iter().enumerate().all(|(i, x)| x.0 == i));
+    assert_eq!(merge::merge_vecs(Vec::new(), vec![1, 2], |x, y| x.cmp(y)), vec![1, 2]);
+    assert_eq!(merge::merge_vecs(vec![1, 2], Vec::new(), |x, y| x.cmp(y)), vec![1, 2]);
+}
+
+/// Test the split points against counting them out by hand.
+#[test]
+fn co_rank() {
+    let first = [1, 2, 2, 2, 5, 7, 7];
+    let second = [0, 2, 2, 6, 7, 9];
+    let mut merged: Vec<(usize, bool)> = first.iter().map(|&x| (x, true)).chain(second.iter().map(|&x| (x, false))).collect();
+    merged.sort_by_key(|a| a.0);
+    for pos in 0..=merged.len() {
+        let from_first = merged[..pos].iter().filter(|a| a.1).count();
+        assert_eq!(merge::co_rank(pos, &first, &second, |a: &usize, b: &usize| Ok::<_, ()>(a.cmp(b))), Ok(from_first));
+    }
+}
+
+/// Test that merging in place with no scratch space finishes, and loses
+/// nothing, when the comparator keeps changing its mind.
+#[test]
+fn rotate_bad_comparator() {
+    use std::mem::MaybeUninit;
+    use sorter::TimSorter;
+    use sort::sort_with_buffer;
+    let answers = [Ordering::Greater, Ordering::Equal, Ordering::Less, Ordering::Less];
+    for &len in &[2, 3, 10, 130, 1000] {
+        for &use_sorter in &[false, true] {
+            let mut list: Vec<usize> = (0..len).collect();
+            let mut calls = 0;
+            let c = |_: &usize, _: &usize| {
+                calls += 1;
+                assert!(calls < 100 * len * len, "the merge doesn't finish");
+                answers[calls % answers.len()]
+            };
+            if use_sorter {
+                TimSorter::with_max_scratch(0).sort_by(&mut list, c);
+            } else {
+                let buf: &mut [MaybeUninit<usize>] = &mut [];
+                sort_with_buffer(&mut list, buf, c);
+            }
+            list.sort();
+            assert!(list.iter().enumerate().all(|(i, &x)| x == i));
+        }
+    }
+}
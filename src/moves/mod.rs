@@ -0,0 +1,76 @@
+//! Hooks for following every move the sort makes, so that the same moves can
+//! be made somewhere else, such as in the companion slices of a `ZipSort`.
+
+#[cfg(test)]
+mod tests;
+
+/// Gets told about every move the sort makes, right after it makes it.
+///
+/// Positions are indices into the list that was handed to the sort, and into
+/// the scratch space for the merge that is going on. Every method does nothing
+/// unless it is overridden, and `()` is what the ordinary sorts use.
+///
+/// The scratch space moves are unsafe to make: elements are moved out to it
+/// and back with plain copies, leaving holes behind, and only the whole
+/// sequence of them leaves every element in the list exactly once. The sort
+/// makes the moves that fill the holes back in even when the comparator fails
+/// or panics, so following along keeps everything whole.
+pub trait Moves {
+    /// `list[start..end]` was reversed.
+    fn reverse(&mut self, _start: usize, _end: usize) {}
+
+    /// `list[start..end]` was rotated left, so that `list[mid]` is now at
+    /// `start`.
+    fn rotate(&mut self, _start: usize, _mid: usize, _end: usize) {}
+
+    /// `len` elements were moved from `list[start..]` out to the start of the
+    /// scratch space, leaving a hole behind.
+    unsafe fn stash(&mut self, _start: usize, _len: usize) {}
+
+    /// `len` elements were moved from `list[src..]` to `list[dest..]`. The two
+    /// ranges may overlap.
+    unsafe fn shift(&mut self, _src: usize, _dest: usize, _len: usize) {}
+
+    /// `len` elements were moved from the scratch space at `src` back into
+    /// the hole at `list[dest..]`.
+    unsafe fn unstash(&mut self, _src: usize, _dest: usize, _len: usize) {}
+}
+
+/// Following along does nothing at all.
+impl Moves for () {}
+
+/// Passes every move on to `moves`, with its positions in the list `by`
+/// further along. The sort uses this when it hands part of the list to
+/// something that only knows about that part.
+pub struct Offset<'a, M: 'a + Moves + ?Sized> {
+    moves: &'a mut M,
+    by: usize,
+}
+
+impl<'a, M: 'a + Moves + ?Sized> Offset<'a, M> {
+    pub fn new(moves: &'a mut M, by: usize) -> Offset<'a, M> {
+        Offset { moves, by }
+    }
+}
+
+impl<'a, M: 'a + Moves + ?Sized> Moves for Offset<'a, M> {
+    fn reverse(&mut self, start: usize, end: usize) {
+        self.moves.reverse(self.by + start, self.by + end);
+    }
+
+    fn rotate(&mut self, start: usize, mid: usize, end: usize) {
+        self.moves.rotate(self.by + start, self.by + mid, self.by + end);
+    }
+
+    unsafe fn stash(&mut self, start: usize, len: usize) {
+        self.moves.stash(self.by + start, len);
+    }
+
+    unsafe fn shift(&mut self, src: usize, dest: usize, len: usize) {
+        self.moves.shift(self.by + src, self.by + dest, len);
+    }
+
+    unsafe fn unstash(&mut self, src: usize, dest: usize, len: usize) {
+        self.moves.unstash(src, self.by + dest, len);
+    }
+}
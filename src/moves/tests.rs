@@ -0,0 +1,89 @@
+//! Hooks for following every move the sort makes.
+
+use core::cmp::Ordering;
+use core::mem::MaybeUninit;
+use std::vec::Vec;
+use moves::{Moves, Offset};
+use policy::{MergePolicy, Timsort, Powersort, Peeksort};
+use sort::{try_sort_in, RunStack};
+use scratch::Scratch;
+use test_util::{pseudo_random, tagged};
+
+/// Makes every move again in a list of where each element started out.
+struct Follow {
+    list: Vec<usize>,
+    tmp: Vec<usize>,
+}
+
+impl Follow {
+    fn new(len: usize) -> Follow {
+        Follow {
+            list: (0..len).collect(),
+            tmp: vec![0; len],
+        }
+    }
+}
+
+impl Moves for Follow {
+    fn reverse(&mut self, start: usize, end: usize) {
+        self.list[start..end].reverse();
+    }
+
+    fn rotate(&mut self, start: usize, mid: usize, end: usize) {
+        self.list[start..end].rotate_left(mid - start);
+    }
+
+    unsafe fn stash(&mut self, start: usize, len: usize) {
+        self.tmp[..len].copy_from_slice(&self.list[start..start + len]);
+    }
+
+    unsafe fn shift(&mut self, src: usize, dest: usize, len: usize) {
+        self.list.copy_within(src..src + len, dest);
+    }
+
+    unsafe fn unstash(&mut self, src: usize, dest: usize, len: usize) {
+        self.list[dest..dest + len].copy_from_slice(&self.tmp[src..src + len]);
+    }
+}
+
+/// Sorts a tagged list, and checks that following along put every tag where
+/// its element went.
+fn check<P: MergePolicy, S: Scratch<(usize, usize)> + ?Sized>(len: usize, policy: &mut P, tmp: &mut S) {
+    let mut list = tagged(pseudo_random(len, 101, 3));
+    let mut follow = Follow::new(len);
+    try_sort_in(&mut list, |a, b| Ok::<Ordering, ()>(a.0.cmp(&b.0)), policy, &mut RunStack::new(), tmp, &mut (), &mut follow).unwrap();
+    let tags: Vec<usize> = list.iter().map(|x| x.1).collect();
+    assert_eq!(follow.list, tags);
+}
+
+/// Test following every kind of move, with each policy, through the scratch
+/// space and in place.
+#[test]
+fn follow() {
+    for &len in &[0, 1, 10, 100, 1000, 5000] {
+        let none: &mut [MaybeUninit<(usize, usize)>] = &mut [];
+        check(len, &mut Timsort, &mut Vec::new());
+        check(len, &mut Powersort, &mut Vec::new());
+        check(len, &mut Peeksort, &mut Vec::new());
+        check(len, &mut Powersort, &mut [MaybeUninit::uninit(); 16][..]);
+        check(len, &mut Peeksort, none);
+    }
+}
+
+/// Test that an offset moves positions in the list, but not in the scratch
+/// space.
+#[test]
+fn offset() {
+    let mut follow = Follow::new(6);
+    {
+        let mut m = Offset::new(&mut follow, 2);
+        m.reverse(0, 2);
+        m.rotate(0, 1, 4);
+        unsafe {
+            m.stash(0, 1);
+            m.shift(1, 0, 3);
+            m.unstash(0, 3, 1);
+        }
+    }
+    assert_eq!(follow.list, [0, 1, 4, 5, 3, 2]);
+}
@@ -0,0 +1,67 @@
+//! Hooks for watching the sort as it goes: which runs it finds, how it merges
+//! them, and when it gallops.
+
+#[cfg(test)]
+mod tests;
+
+use sort::Run;
+
+/// How two runs were merged.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MergeKind {
+    /// Nothing had to move, because the runs were already in order.
+    InOrder,
+    /// From low to high, with the first run copied out to the scratch space.
+    Lo,
+    /// From high to low, with the second run copied out to the scratch space.
+    Hi,
+    /// In place, by rotating pieces of the runs into position, because
+    /// neither run fit in the scratch space.
+    InPlace,
+}
+
+/// Gets told about each step of the sort as it happens.
+///
+/// Every method does nothing unless it is overridden, and the sort is
+/// compiled separately for each observer, so sorting with `()` as the
+/// observer costs nothing at all.
+pub trait SortObserver {
+    /// A run of `len` elements starting at `pos` that was already in order
+    /// was found. If it was in descending order, it has been `reversed`.
+    fn run_found(&mut self, _pos: usize, _len: usize, _reversed: bool) {}
+
+    /// The run at `pos` was lengthened from `from` to `to` elements by
+    /// sorting the ones after it into it.
+    fn run_extended(&mut self, _pos: usize, _from: usize, _to: usize) {}
+
+    /// The run `first` was merged with the run `second` right after it.
+    /// Both are given as they were before the merge. `index` is where
+    /// `first` was on the run stack, or zero for `Peeksort`, which has none.
+    fn runs_merged(&mut self, _index: usize, _first: Run, _second: Run, _kind: MergeKind) {}
+
+    /// `first_len` elements are about to be merged with the `second_len`
+    /// elements after them, going from low to high. Merges done in place
+    /// are made of several of these.
+    fn merge_lo(&mut self, _first_len: usize, _second_len: usize) {}
+
+    /// `first_len` elements are about to be merged with the `second_len`
+    /// elements after them, going from high to low.
+    fn merge_hi(&mut self, _first_len: usize, _second_len: usize) {}
+
+    /// A merge started galloping.
+    fn gallop_enter(&mut self) {}
+
+    /// A merge stopped galloping, because it wasn't paying off.
+    fn gallop_exit(&mut self) {}
+
+    /// A gallop moved `count` elements in one go.
+    fn galloped(&mut self, _count: usize) {}
+
+    /// Room for `len` elements was taken from the scratch space.
+    fn scratch(&mut self, _len: usize) {}
+
+    /// `count` elements were moved from one place to another.
+    fn moved(&mut self, _count: usize) {}
+}
+
+impl SortObserver for () {}
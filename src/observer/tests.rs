@@ -0,0 +1,75 @@
+//! Hooks for watching the sort as it goes.
+
+use std::vec::Vec;
+use observer::{MergeKind, SortObserver};
+use sort::{sort_with_observer, Run};
+
+/// Keeps track of which parts of the list are known to be sorted.
+#[derive(Default)]
+struct Runs {
+    runs: Vec<(usize, usize)>,
+    merges: Vec<MergeKind>,
+    gallops: isize,
+}
+
+impl SortObserver for Runs {
+    fn run_found(&mut self, pos: usize, len: usize, _reversed: bool) {
+        self.runs.push((pos, len));
+    }
+
+    fn run_extended(&mut self, pos: usize, from: usize, to: usize) {
+        assert_eq!(self.runs.pop(), Some((pos, from)));
+        self.runs.push((pos, to));
+    }
+
+    fn runs_merged(&mut self, index: usize, first: Run, second: Run, kind: MergeKind) {
+        assert_eq!(self.runs[index], (first.pos, first.len));
+        assert_eq!(self.runs[index + 1], (second.pos, second.len));
+        self.runs[index] = (first.pos, first.len + second.len);
+        self.runs.remove(index + 1);
+        self.merges.push(kind);
+    }
+
+    fn gallop_enter(&mut self) {
+        self.gallops += 1;
+    }
+
+    fn gallop_exit(&mut self) {
+        self.gallops -= 1;
+        assert!(self.gallops >= 0);
+    }
+}
+
+/// Test that the runs found and merged add up to the whole list.
+#[test]
+fn runs_add_up() {
+    let len = 10_000;
+    let mut list: Vec<usize> = (0..len).map(|i| (i * 7919) % 1009 + (i / 2000) * 1009).collect();
+    let mut runs = Runs::default();
+    sort_with_observer(&mut list, &mut runs, |a, b| a.cmp(b));
+    assert!(list.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(runs.runs, vec![(0, len)]);
+    assert!(!runs.merges.is_empty());
+    assert!(runs.merges.iter().all(|&k| k != MergeKind::InPlace));
+}
+
+/// Test that descending runs are reported as reversed, and that merging runs
+/// that are already in order is reported as such.
+#[test]
+fn reversed_and_in_order() {
+    struct Reversed(Vec<bool>, Vec<MergeKind>);
+    impl SortObserver for Reversed {
+        fn run_found(&mut self, _pos: usize, _len: usize, reversed: bool) {
+            self.0.push(reversed);
+        }
+        fn runs_merged(&mut self, _index: usize, _first: Run, _second: Run, kind: MergeKind) {
+            self.1.push(kind);
+        }
+    }
+    let mut list: Vec<usize> = (0..100).rev().collect();
+    list.extend(100..200);
+    let mut reversed = Reversed(Vec::new(), Vec::new());
+    sort_with_observer(&mut list, &mut reversed, |a, b| a.cmp(b));
+    assert_eq!(reversed.0, vec![true, false]);
+    assert_eq!(reversed.1, vec![MergeKind::InOrder]);
+}
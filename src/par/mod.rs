@@ -0,0 +1,181 @@
+//! The parallel sort. The list is cut into one chunk per thread, the chunks
+//! are sorted at the same time, and then neighbouring chunks are merged in
+//! pairs, again at the same time, until there is only one left. Once there are
+//! fewer merges left than threads, each merge is itself split between threads.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::mem::MaybeUninit;
+use core::ptr;
+use std::panic;
+use std::thread;
+use alloc::vec::Vec;
+use merge::{co_rank, merge, MIN_GALLOP};
+use policy::Powersort;
+use sort::{try_sort, try_sort_in, RunStack};
+
+/// Chunks shorter than this aren't worth starting a thread for.
+const MIN_CHUNK_LEN: usize = 4096;
+
+/// Sorts the list using merge sort, on as many threads as the machine has
+/// cores.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+/// The sort is stable, just like `sort_by`.
+pub fn par_sort<T: Send, C: Fn(&T, &T) -> Ordering + Sync>(list: &mut [T], c: C) {
+    par_sort_with_threads(list, default_threads(), c)
+}
+
+/// Sorts the list using merge sort, on up to `threads` threads.
+///
+/// Short lists use fewer threads than that, and a list of less than a few
+/// thousand elements is sorted on the calling thread alone.
+pub fn par_sort_with_threads<T: Send, C: Fn(&T, &T) -> Ordering + Sync>(list: &mut [T], threads: usize, c: C) {
+    match try_par_sort_with_threads(list, threads, |a, b| Ok::<Ordering, Infallible>(c(a, b))) {
+        Ok(()) => (),
+        Err(e) => match e {},
+    }
+}
+
+/// Sorts the list using merge sort, on as many threads as the machine has
+/// cores, with a comparator that can fail.
+///
+/// See `try_sort_by` for what happens when it does. If it fails on more than
+/// one thread, the error from the leftmost chunk is returned.
+pub fn try_par_sort<T: Send, E: Send, C: Fn(&T, &T) -> Result<Ordering, E> + Sync>(list: &mut [T], c: C) -> Result<(), E> {
+    try_par_sort_with_threads(list, default_threads(), c)
+}
+
+/// Sorts the list using merge sort, on up to `threads` threads, with a
+/// comparator that can fail.
+pub fn try_par_sort_with_threads<T: Send, E: Send, C: Fn(&T, &T) -> Result<Ordering, E> + Sync>(list: &mut [T], threads: usize, c: C) -> Result<(), E> {
+    let len = list.len();
+    let threads = threads.min(len / MIN_CHUNK_LEN).max(1);
+    if threads == 1 {
+        return try_sort(list, &c);
+    }
+    let chunk_len = (len + threads - 1) / threads;
+    let c = &c;
+    // Every thread works in its own part of the same scratch buffer.
+    let mut tmp: Vec<T> = Vec::with_capacity(len);
+    // Where each sorted chunk starts, plus the end of the list.
+    let mut bounds: Vec<usize> = (0..len).step_by(chunk_len).collect();
+    bounds.push(len);
+    thread::scope(|s| {
+        let tmp = &mut tmp.spare_capacity_mut()[..len];
+        let workers: Vec<_> = list.chunks_mut(chunk_len).zip(tmp.chunks_mut(chunk_len)).map(|(chunk, tmp)| {
+            s.spawn(move || try_sort_in(chunk, c, &mut Powersort, &mut RunStack::new(), tmp, &mut (), &mut ()))
+        }).collect();
+        join_all(workers)
+    })?;
+    while bounds.len() > 2 {
+        // There are fewer merges than threads in the later rounds, so each
+        // one is split up between the threads that would otherwise be idle.
+        let threads_per_merge = threads / ((bounds.len() - 1) / 2);
+        thread::scope(|s| {
+            let mut workers = Vec::new();
+            let mut rest = &mut *list;
+            let mut rest_tmp = &mut tmp.spare_capacity_mut()[..len];
+            for pair in bounds.windows(3).step_by(2) {
+                let (l, r) = rest.split_at_mut(pair[2] - pair[0]);
+                rest = r;
+                let (t, r) = rest_tmp.split_at_mut(l.len());
+                rest_tmp = r;
+                let first_len = pair[1] - pair[0];
+                workers.push(s.spawn(move || par_merge(l, first_len, threads_per_merge, c, t)));
+            }
+            join_all(workers)
+        })?;
+        // Every other boundary is gone now, apart from the end of the list,
+        // and the start of an odd chunk out at the end.
+        let last = bounds.len() - 1;
+        bounds = bounds.iter().enumerate().filter(|&(i, _)| i % 2 == 0 || i == last).map(|(_, &b)| b).collect();
+    }
+    Ok(())
+}
+
+/// Merges `list[..first_len]` with `list[first_len..]`, on up to `threads`
+/// threads. `tmp` must have room for the whole list.
+///
+/// The merged list is cut into one piece per thread. For each cut, `co_rank`
+/// finds how many of the elements before it come from the first run, which
+/// gives matching split points in both runs. The pieces of the two runs that
+/// belong together are moved next to each other, and then every piece is
+/// merged on its own thread, using its own part of `tmp`.
+pub fn par_merge<T: Send, E: Send, C: Fn(&T, &T) -> Result<Ordering, E> + Sync>(list: &mut [T], first_len: usize, threads: usize, c: &C, tmp: &mut [MaybeUninit<T>]) -> Result<(), E> {
+    let len = list.len();
+    debug_assert!(tmp.len() >= len);
+    let threads = threads.min(len / MIN_CHUNK_LEN);
+    if threads <= 1 {
+        return merge(list, first_len, c, tmp, &mut { MIN_GALLOP }, &mut (), &mut ()).map(|_| ());
+    }
+    // Where each piece starts in the output, and in the first run. All of the
+    // comparing is done before anything is moved.
+    let mut splits: Vec<(usize, usize)> = Vec::with_capacity(threads + 1);
+    {
+        let (first, second) = list.split_at(first_len);
+        let (mut prev_pos, mut prev_a) = (0, 0);
+        for i in 0..threads {
+            let pos = len * i / threads;
+            // A comparator that contradicts itself could make the pieces
+            // overlap, so each one is kept between the last one and the
+            // next cut, even though that would never change a good answer.
+            let a = co_rank(pos, first, second, c)?.max(prev_a).min(prev_a + (pos - prev_pos));
+            splits.push((pos, a));
+            prev_pos = pos;
+            prev_a = a;
+        }
+    }
+    splits.push((len, first_len));
+    unsafe {
+        // Nothing between here and the end of the block can fail, so the
+        // list never has any holes in it when a comparator could panic.
+        let list_ptr = list.as_mut_ptr();
+        let tmp_ptr = tmp.as_mut_ptr() as *mut T;
+        ptr::copy_nonoverlapping(list_ptr, tmp_ptr, len);
+        for w in splits.windows(2) {
+            let (pos, a) = w[0];
+            let (next_pos, next_a) = w[1];
+            let (b, next_b) = (first_len + pos - a, first_len + next_pos - next_a);
+            ptr::copy_nonoverlapping(tmp_ptr.add(a), list_ptr.add(pos), next_a - a);
+            ptr::copy_nonoverlapping(tmp_ptr.add(b), list_ptr.add(pos + next_a - a), next_b - b);
+        }
+    }
+    thread::scope(|s| {
+        let mut workers = Vec::with_capacity(threads);
+        let mut rest = &mut *list;
+        let mut rest_tmp = &mut *tmp;
+        for w in splits.windows(2) {
+            let (pos, a) = w[0];
+            let (next_pos, next_a) = w[1];
+            let (l, r) = rest.split_at_mut(next_pos - pos);
+            rest = r;
+            let (t, r) = rest_tmp.split_at_mut(next_pos - pos);
+            rest_tmp = r;
+            workers.push(s.spawn(move || merge(l, next_a - a, c, t, &mut { MIN_GALLOP }, &mut (), &mut ()).map(|_| ())));
+        }
+        join_all(workers)
+    })
+}
+
+/// Waits for every worker, then returns the first error any of them hit.
+/// If one of them panicked, the panic is passed on.
+fn join_all<E>(workers: Vec<thread::ScopedJoinHandle<Result<(), E>>>) -> Result<(), E> {
+    let mut result = Ok(());
+    for worker in workers {
+        match worker.join() {
+            Ok(r) => if result.is_ok() {
+                result = r;
+            },
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+    result
+}
+
+fn default_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
@@ -0,0 +1,97 @@
+//! The parallel sort.
+
+use std::vec::Vec;
+use std::cmp::Ordering;
+use par;
+use test_util::{check_sorted, pseudo_random, tagged};
+
+/// Test the parallel sort on different numbers of threads, including ones
+/// that leave an odd chunk out when merging.
+#[test]
+fn threads() {
+    let len = 50_000;
+    let list = tagged(pseudo_random(len, 1009, 1));
+    for threads in 0..8 {
+        let mut list = list.clone();
+        par::par_sort_with_threads(&mut list, threads, |a, b| a.0.cmp(&b.0));
+        check_sorted(&list);
+    }
+}
+
+/// Test that short lists are still sorted.
+#[test]
+fn short() {
+    for len in 0..100 {
+        let mut list: Vec<(usize, usize)> = (0..len).map(|i| ((i * 31) % 7, i)).collect();
+        par::par_sort(&mut list, |a, b| a.0.cmp(&b.0));
+        check_sorted(&list);
+    }
+}
+
+/// Test that an error on one thread stops the sort, and that the list still
+/// holds every element.
+#[test]
+fn error() {
+    let len = 50_000;
+    let mut list = pseudo_random(len, len, 0);
+    let r = par::try_par_sort_with_threads(&mut list, 4, |a, b| {
+        if *a == 12345 || *b == 12345 {
+            Err(())
+        } else {
+            Ok(a.cmp(b))
+        }
+    });
+    assert_eq!(r, Err(()));
+    list.sort();
+    assert!(list.iter().enumerate().all(|(i, &x)| i == x));
+}
+
+/// Test that a panic in the comparator comes out of the sort, without losing
+/// any elements.
+#[test]
+fn panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    let len = 50_000;
+    let mut list = pseudo_random(len, len, 0);
+    let r = catch_unwind(AssertUnwindSafe(|| {
+        par::par_sort_with_threads(&mut list, 4, |a, b| {
+            if *a == 12345 {
+                panic!("bad element");
+            }
+            a.cmp(b)
+        });
+    }));
+    assert!(r.is_err());
+    list.sort();
+    assert!(list.iter().enumerate().all(|(i, &x)| i == x));
+}
+
+/// Test sorting in descending order, with a comparator that is used again
+/// afterwards.
+#[test]
+fn descending() {
+    let len = 20_000;
+    let mut list = pseudo_random(len, len, 0);
+    let reverse = |a: &usize, b: &usize| -> Ordering { b.cmp(a) };
+    par::par_sort_with_threads(&mut list, 3, reverse);
+    for i in 1..len {
+        assert_ne!(reverse(&list[i - 1], &list[i]), Ordering::Greater);
+    }
+}
+
+/// Test merging two runs on several threads, with lots of equal elements
+/// straddling the split points.
+#[test]
+fn merge_stable() {
+    use std::mem::MaybeUninit;
+    for &(first_len, second_len) in &[(20_000, 20_000), (35_000, 5_000), (1, 40_000), (40_000, 0)] {
+        let mut list: Vec<(usize, usize)> = (0..first_len).map(|i| (i / 1000, i)).collect();
+        list.extend((0..second_len).map(|i| (i / 700, first_len + i)));
+        let mut tmp: Vec<MaybeUninit<(usize, usize)>> = (0..list.len()).map(|_| MaybeUninit::uninit()).collect();
+        for threads in 1..6 {
+            let mut list = list.clone();
+            par::par_merge(&mut list, first_len, threads, &|a: &(usize, usize), b: &(usize, usize)| Ok::<_, ()>(a.0.cmp(&b.0)), &mut tmp).unwrap();
+            check_sorted(&list);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Sorting a list without moving it, by sorting its indices instead, and
+//! putting a list into an order given by its indices.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::Ordering;
+use alloc::vec::Vec;
+use indirect::permute;
+use sort::sort;
+
+/// Returns the indices of the elements of `list` in sorted order, leaving the
+/// list itself alone: `list[perm[0]]` is the smallest element, and so on.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+/// The sort is stable, so the indices of equal elements stay in increasing
+/// order.
+///
+/// ```
+/// let list = ["b", "c", "a"];
+/// assert_eq!(timsort::argsort_by(&list, |a, b| a.cmp(b)), vec![2, 0, 1]);
+/// ```
+pub fn argsort_by<T, C: FnMut(&T, &T) -> Ordering>(list: &[T], mut c: C) -> Vec<usize> {
+    let mut perm: Vec<usize> = (0..list.len()).collect();
+    sort(&mut perm, |&i, &j| c(&list[i], &list[j]));
+    perm
+}
+
+/// Like `argsort_by`, for types with a total order.
+pub fn argsort<T: Ord>(list: &[T]) -> Vec<usize> {
+    argsort_by(list, T::cmp)
+}
+
+/// Reorders `list` so that the element at `perm[i]` ends up at `i`, which
+/// sorts it if `perm` came from `argsort_by`.
+///
+/// Each cycle of the permutation is followed round, the way `sort_by_indirect`
+/// does it, so every element is moved once, apart from one per cycle, which
+/// is moved twice. Keeping track of the cycles takes a copy of `perm`.
+///
+/// # Panics
+///
+/// If `perm` isn't a permutation of `0..list.len()`. The list is checked
+/// before anything is moved, so it is left as it was.
+pub fn apply_permutation<T>(list: &mut [T], perm: &[usize]) {
+    assert_eq!(list.len(), perm.len(), "the permutation is the wrong length");
+    let mut seen = alloc::vec![false; list.len()];
+    for &i in perm {
+        assert!(i < seen.len() && !seen[i], "not a permutation");
+        seen[i] = true;
+    }
+    let mut perm = perm.to_vec();
+    unsafe {
+        permute(list, &mut perm);
+    }
+}
+
+/// Returns the permutation that undoes `perm`: if `perm[i] == j`, then
+/// `inverse[j] == i`. For a permutation from `argsort_by`, that is the rank of
+/// each element.
+///
+/// # Panics
+///
+/// If `perm` isn't a permutation of `0..perm.len()`.
+pub fn invert_permutation(perm: &[usize]) -> Vec<usize> {
+    let len = perm.len();
+    // `len` marks the places that haven't been filled yet.
+    let mut inverse = alloc::vec![len; len];
+    for (i, &j) in perm.iter().enumerate() {
+        assert!(j < len && inverse[j] == len, "not a permutation");
+        inverse[j] = i;
+    }
+    inverse
+}
@@ -0,0 +1,67 @@
+//! Sorting a list by sorting its indices, and putting a list into an order
+//! given by its indices.
+
+use std::string::{String, ToString};
+use std::vec::Vec;
+use permute::{argsort, argsort_by, apply_permutation, invert_permutation};
+use test_util::{check_sorted_by, pseudo_random};
+
+/// Test that the indices come out in sorted, stable order, and that applying
+/// them sorts the list.
+#[test]
+fn argsort_then_apply() {
+    for &len in &[0, 1, 2, 10, 100, 1000] {
+        let list = pseudo_random(len, 101, 1);
+        let perm = argsort(&list);
+        check_sorted_by(&perm, |&i| (list[i], i));
+        // Strings, so that a lost or duplicated element would be noticed.
+        let mut strings: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+        apply_permutation(&mut strings, &perm);
+        let expected: Vec<String> = perm.iter().map(|&i| list[i].to_string()).collect();
+        assert_eq!(strings, expected);
+    }
+}
+
+/// Test that a permutation and its inverse undo each other.
+#[test]
+fn invert() {
+    let list = pseudo_random(500, 101, 1);
+    let perm = argsort_by(&list, |a, b| b.cmp(a));
+    let inverse = invert_permutation(&perm);
+    for i in 0..perm.len() {
+        assert_eq!(inverse[perm[i]], i);
+    }
+    let mut sorted = list.clone();
+    apply_permutation(&mut sorted, &perm);
+    apply_permutation(&mut sorted, &inverse);
+    assert_eq!(sorted, list);
+}
+
+/// Test that something that isn't a permutation is refused.
+#[test]
+#[should_panic(expected = "not a permutation")]
+fn apply_repeated() {
+    apply_permutation(&mut [1, 2, 3], &[1, 1, 0]);
+}
+
+/// Test that a list is left as it was when the permutation is refused, even
+/// if the repeat comes after a whole cycle.
+#[test]
+fn apply_refused_untouched() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    let mut list = [1, 2, 3, 4];
+    assert!(catch_unwind(AssertUnwindSafe(|| apply_permutation(&mut list, &[1, 0, 3, 3]))).is_err());
+    assert_eq!(list, [1, 2, 3, 4]);
+}
+
+#[test]
+#[should_panic(expected = "not a permutation")]
+fn invert_repeated() {
+    invert_permutation(&[0, 2, 2]);
+}
+
+#[test]
+#[should_panic]
+fn apply_out_of_range() {
+    apply_permutation(&mut [1, 2, 3], &[0, 1, 3]);
+}
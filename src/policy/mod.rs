@@ -0,0 +1,175 @@
+//! Merge policies: the rules that decide which runs get merged, and when.
+//!
+//! Runs are found from left to right and pushed onto a stack. After each push,
+//! the policy picks pairs of neighbouring runs to merge until it is happy with
+//! the shape of the stack. Once the whole list has been pushed, it picks the
+//! order the leftover runs are merged in. How well it picks decides how much
+//! merging the sort does, and how deep the stack gets.
+
+#[cfg(test)]
+mod tests;
+
+use sort::Run;
+
+/// Decides which runs on the stack get merged.
+///
+/// A policy has to keep the stack short. It has room for 96 runs, and pushing
+/// another one onto a full stack panics. Every run but the last is at least
+/// 32 elements long, so a policy that keeps each run on the stack longer than
+/// the one above it is always safe.
+pub trait MergePolicy {
+    /// Called after a run has been pushed onto `runs`, and again after every
+    /// merge it asks for, until it returns `None`.
+    ///
+    /// Returns `Some(i)` to merge `runs[i]` with `runs[i + 1]`. The merged run
+    /// takes the place of `runs[i]`. `powers` has one number per run for the
+    /// policy to keep whatever it likes in: it is zero when a run is pushed,
+    /// and a merged run keeps the power of `runs[i + 1]`. `list_len` is the
+    /// length of the whole list being sorted.
+    fn merge_collapse(&mut self, runs: &[Run], powers: &mut [u32], list_len: usize) -> Option<usize>;
+
+    /// Called once every run has been pushed, until only one run is left.
+    /// Returns which run to merge with the one above it.
+    ///
+    /// By default, the top run is merged with the shorter of its neighbours.
+    fn merge_force_collapse(&mut self, runs: &[Run], _list_len: usize) -> usize {
+        let n = runs.len() - 2;
+        if n > 0 && runs[n - 1].len < runs[n + 1].len {
+            n - 1
+        } else {
+            n
+        }
+    }
+
+    /// Whether the list should be split top-down instead of being walked from
+    /// left to right. A policy that returns `true` is never asked about the
+    /// stack at all; see `Peeksort`.
+    fn top_down(&self) -> bool {
+        false
+    }
+}
+
+/// The rule from the original TimSort: each run on the stack must be longer
+/// than the two above it put together.
+///
+/// Copied almost verbatim from
+/// http://envisage-project.eu/proving-android-java-and-python-sorting-algorithm-is-broken-and-how-to-fix-it/#sec3.2
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Timsort;
+
+impl MergePolicy for Timsort {
+    fn merge_collapse(&mut self, runs: &[Run], _powers: &mut [u32], _list_len: usize) -> Option<usize> {
+        if runs.len() < 2 {
+            return None;
+        }
+        let n = runs.len() - 2;
+        if    (n >= 1 && runs[n - 1].len <= runs[n].len + runs[n + 1].len)
+           || (n >= 2 && runs[n - 2].len <= runs[n].len + runs[n - 1].len) {
+            if runs[n - 1].len < runs[n + 1].len {
+                Some(n - 1)
+            } else {
+                Some(n)
+            }
+        } else {
+            None // Invariant established.
+        }
+    }
+}
+
+/// Powersort, by Munro and Wild, which CPython has used since 3.11.
+///
+/// Each boundary between two runs is given a power: how many times the list
+/// has to be cut in half before the midpoints of the two runs land in
+/// different halves. The stack is kept in strictly increasing order of power,
+/// which gives a merge tree close to the best possible one.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Powersort;
+
+impl MergePolicy for Powersort {
+    fn merge_collapse(&mut self, runs: &[Run], powers: &mut [u32], list_len: usize) -> Option<usize> {
+        if runs.len() < 2 {
+            return None;
+        }
+        let n = runs.len() - 1;
+        // Each run's power is that of the boundary above it. The run below a
+        // fresh one has no power yet. After a merge, the merged run keeps the
+        // power of the boundary above it.
+        if powers[n - 1] == 0 {
+            powers[n - 1] = node_power(runs[n - 1], runs[n], list_len);
+        }
+        if n >= 2 && powers[n - 2] > powers[n - 1] {
+            Some(n - 2)
+        } else {
+            None
+        }
+    }
+}
+
+/// Computes the power of the boundary between `a` and the run right after
+/// it, `b`. This is `powerloop` from CPython's `listobject.c`.
+fn node_power(a: Run, b: Run, list_len: usize) -> u32 {
+    debug_assert_eq!(a.pos + a.len, b.pos);
+    // Twice the midpoints of the two runs. Doubling them can't overflow a
+    // `u128`, even for a list of `usize::MAX` zero-sized elements.
+    let n = list_len as u128;
+    let mut x = 2 * a.pos as u128 + a.len as u128;
+    let mut y = x + a.len as u128 + b.len as u128;
+    let mut power = 0;
+    loop {
+        power += 1;
+        if x >= n {
+            // Both midpoints are in the top half.
+            x -= n;
+            y -= n;
+        } else if y >= n {
+            // They have been split up.
+            break;
+        }
+        x <<= 1;
+        y <<= 1;
+    }
+    power
+}
+
+/// Shivers' sort: the top two runs are merged as long as the lower one is no
+/// longer than the top one, rounding both lengths down to a power of two.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ShiversSort;
+
+impl MergePolicy for ShiversSort {
+    fn merge_collapse(&mut self, runs: &[Run], _powers: &mut [u32], _list_len: usize) -> Option<usize> {
+        if runs.len() < 2 {
+            return None;
+        }
+        let n = runs.len() - 2;
+        if log2(runs[n].len) <= log2(runs[n + 1].len) {
+            Some(n)
+        } else {
+            None
+        }
+    }
+}
+
+fn log2(len: usize) -> u32 {
+    usize::BITS - 1 - len.leading_zeros()
+}
+
+/// Peeksort, by Munro and Wild.
+///
+/// Instead of walking the list from left to right, it looks at the run that
+/// covers the middle of the list, splits the list at whichever end of that run
+/// is closer to the middle, and sorts each half the same way before merging
+/// them. It needs no run stack, but it only spots ascending runs: descending
+/// ones are sorted like any other unsorted section.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Peeksort;
+
+impl MergePolicy for Peeksort {
+    fn merge_collapse(&mut self, _runs: &[Run], _powers: &mut [u32], _list_len: usize) -> Option<usize> {
+        None
+    }
+
+    fn top_down(&self) -> bool {
+        true
+    }
+}
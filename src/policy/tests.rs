@@ -0,0 +1,126 @@
+//! Merge policies: the rules that decide which runs get merged, and when.
+
+use std::vec::Vec;
+use core::cmp::Ordering;
+use policy::{node_power, MergePolicy, Timsort, Powersort, ShiversSort, Peeksort};
+use sort::{try_sort_in, Run, RunStack};
+use test_util::{check_sorted, pseudo_random, tagged};
+
+/// Sorts with the given policy, and checks the result is sorted and stable.
+fn check_sort<P: MergePolicy>(policy: &mut P, list: &mut [(usize, usize)]) {
+    let mut tmp = Vec::new();
+    try_sort_in(list, |a, b| Ok::<Ordering, ()>(a.0.cmp(&b.0)), policy, &mut RunStack::new(), &mut tmp, &mut (), &mut ()).unwrap();
+    check_sorted(&*list);
+}
+
+/// Lists with a mix of random stretches, ascending runs and descending runs.
+fn lists() -> Vec<Vec<(usize, usize)>> {
+    let mut lists = Vec::new();
+    for &len in &[0, 1, 63, 64, 65, 100, 1000, 5000] {
+        lists.push(tagged(pseudo_random(len, 101, 1)));
+        lists.push((0..len).map(|i| (i / 3, i)).collect());
+        lists.push((0..len).map(|i| ((len - i) / 3, i)).collect());
+        lists.push((0..len).map(|i| if (i / 200) % 2 == 0 { (i % 200, i) } else { (200 - i % 200, i) }).collect());
+        lists.push((0..len).map(|i| ((i * 31) % 97 + (i / 500) * 100, i)).collect());
+    }
+    lists
+}
+
+/// Test that every policy sorts stably.
+#[test]
+fn all_sort() {
+    for list in lists() {
+        check_sort(&mut Timsort, &mut list.clone());
+        check_sort(&mut Powersort, &mut list.clone());
+        check_sort(&mut ShiversSort, &mut list.clone());
+        check_sort(&mut Peeksort, &mut list.clone());
+    }
+}
+
+/// Wraps a policy, recording the deepest the stack gets and how many
+/// elements get merged.
+struct Measure<P> {
+    policy: P,
+    max_runs: usize,
+    cost: usize,
+}
+
+impl<P: MergePolicy> MergePolicy for Measure<P> {
+    fn merge_collapse(&mut self, runs: &[Run], powers: &mut [u32], list_len: usize) -> Option<usize> {
+        self.max_runs = self.max_runs.max(runs.len());
+        let n = self.policy.merge_collapse(runs, powers, list_len);
+        if let Some(n) = n {
+            self.cost += runs[n].len + runs[n + 1].len;
+        }
+        n
+    }
+
+    fn merge_force_collapse(&mut self, runs: &[Run], list_len: usize) -> usize {
+        let n = self.policy.merge_force_collapse(runs, list_len);
+        self.cost += runs[n].len + runs[n + 1].len;
+        n
+    }
+}
+
+fn measure<P: MergePolicy>(policy: P, list: &mut [(usize, usize)]) -> Measure<P> {
+    let mut measure = Measure { policy, max_runs: 0, cost: 0 };
+    check_sort(&mut measure, list);
+    measure
+}
+
+/// Test that a custom policy gets to see the stack, and that the stack stays
+/// short on a list made of many runs.
+#[test]
+fn stack_stays_short() {
+    let list = tagged(pseudo_random(100_000, 64, 0));
+    let timsort = measure(Timsort, &mut list.clone());
+    let powersort = measure(Powersort, &mut list.clone());
+    let shivers = measure(ShiversSort, &mut list.clone());
+    for &max_runs in &[timsort.max_runs, powersort.max_runs, shivers.max_runs] {
+        assert!(max_runs > 1);
+        assert!(max_runs <= 20);
+    }
+    for &cost in &[timsort.cost, powersort.cost, shivers.cost] {
+        assert!(cost >= 100_000);
+    }
+}
+
+/// Test that Powersort beats the TimSort rule on runs of awkward lengths.
+#[test]
+fn powersort_merges_less() {
+    let mut list = Vec::new();
+    for &(start, len) in &[(0, 1000), (0, 70), (0, 70), (0, 1000), (0, 70), (0, 70), (0, 1000)] {
+        for i in 0..len {
+            list.push((start + i, list.len()));
+        }
+    }
+    let timsort = measure(Timsort, &mut list.clone());
+    let powersort = measure(Powersort, &mut list.clone());
+    assert!(powersort.cost <= timsort.cost);
+}
+
+/// Test the power of some boundaries against working them out by hand.
+#[test]
+fn powers() {
+    let run = |pos, len| Run { pos, len };
+    // The midpoints are 25 and 75, either side of the middle.
+    assert_eq!(node_power(run(0, 50), run(50, 50), 100), 1);
+    // 12.5 and 37.5 are both in the bottom half, but either side of 25.
+    assert_eq!(node_power(run(0, 25), run(25, 25), 100), 2);
+    // 62.5 and 87.5 are both in the top half, but either side of 75.
+    assert_eq!(node_power(run(50, 25), run(75, 25), 100), 2);
+    // 50.5 and 51.5 are only split up after cutting the list six times.
+    assert_eq!(node_power(run(50, 1), run(51, 1), 100), 7);
+    // Huge lists don't overflow.
+    assert_eq!(node_power(run(0, usize::MAX / 2), run(usize::MAX / 2, usize::MAX / 2 + 1), usize::MAX), 1);
+}
+
+/// Test that Peeksort spots a sorted list without merging anything.
+#[test]
+fn peeksort_sorted() {
+    let mut list: Vec<usize> = (0..1000).collect();
+    let mut comparisons = 0;
+    try_sort_in(&mut list, |a, b| { comparisons += 1; Ok::<Ordering, ()>(a.cmp(b)) }, &mut Peeksort, &mut RunStack::new(), &mut Vec::new(), &mut (), &mut ()).unwrap();
+    assert!(comparisons <= 2 * 1000);
+    assert!(list.iter().enumerate().all(|(i, &x)| i == x));
+}
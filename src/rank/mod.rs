@@ -0,0 +1,110 @@
+//! Ranking the elements of a list by where they come in sorted order, with a
+//! choice of how ties are ranked.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::Ordering;
+use alloc::vec::Vec;
+use permute::argsort_by;
+use search::upper_bound_by;
+
+/// How elements that compare equal are ranked, as in `scipy.stats.rankdata`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ties {
+    /// Each element gets a different rank, with ties ranked in the order they
+    /// are in the list.
+    Ordinal,
+    /// Ties all get the same rank, and the next element gets the rank after
+    /// that, so the ranks have no gaps.
+    Dense,
+    /// Ties all get the lowest of the ranks they would have had.
+    Min,
+    /// Ties all get the highest of the ranks they would have had.
+    Max,
+    /// Ties all get the mean of the ranks they would have had, which is what
+    /// Spearman's rank correlation uses.
+    Average,
+}
+
+/// The ranks from `rank_by`. Ranking with `Ties::Average` can give halves,
+/// so those ranks are `f64`; every other method gives whole numbers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Ranks {
+    /// Ranks from `Ties::Ordinal`, `Ties::Dense`, `Ties::Min` or `Ties::Max`.
+    Whole(Vec<usize>),
+    /// Ranks from `Ties::Average`.
+    Average(Vec<f64>),
+}
+
+impl Ranks {
+    /// Returns the ranks as `f64`s, whichever method they came from, as
+    /// Spearman's rank correlation wants them.
+    pub fn into_f64(self) -> Vec<f64> {
+        match self {
+            Ranks::Whole(ranks) => ranks.into_iter().map(|rank| rank as f64).collect(),
+            Ranks::Average(ranks) => ranks,
+        }
+    }
+}
+
+/// Returns the rank of each element of `list`, counting from 1 for the
+/// smallest.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+///
+/// ```
+/// use timsort::{Ranks, Ties};
+/// let list = [40, 10, 40, 20];
+/// assert_eq!(timsort::rank_by(&list, |a, b| a.cmp(b), Ties::Average), Ranks::Average(vec![3.5, 1.0, 3.5, 2.0]));
+/// assert_eq!(timsort::rank_by(&list, |a, b| a.cmp(b), Ties::Dense), Ranks::Whole(vec![3, 1, 3, 2]));
+/// ```
+///
+/// The list is sorted indirectly, as by `argsort_by`, and then each group of
+/// ties is found by galloping forward from its first element, the way
+/// `gallop_right` finds the end of a run of equal elements during a merge.
+/// That takes `O(log k)` comparisons for a group of `k`, so the ranks cost
+/// little more than the sort.
+pub fn rank_by<T, C: FnMut(&T, &T) -> Ordering>(list: &[T], mut c: C, ties: Ties) -> Ranks {
+    let perm = argsort_by(list, &mut c);
+    if ties == Ties::Average {
+        let mut ranks = alloc::vec![0.0; list.len()];
+        for_each_group(list, &perm, c, |start, end| {
+            for &i in &perm[start..end] {
+                ranks[i] = (start + 1 + end) as f64 / 2.0;
+            }
+        });
+        return Ranks::Average(ranks);
+    }
+    let mut ranks = alloc::vec![0; list.len()];
+    let mut dense = 0;
+    for_each_group(list, &perm, c, |start, end| {
+        dense += 1;
+        for (offset, &i) in perm[start..end].iter().enumerate() {
+            ranks[i] = match ties {
+                Ties::Ordinal => start + offset + 1,
+                Ties::Dense => dense,
+                Ties::Min => start + 1,
+                Ties::Max => end,
+                Ties::Average => unreachable!(),
+            };
+        }
+    });
+    Ranks::Whole(ranks)
+}
+
+/// Like `rank_by`, for types with a total order.
+pub fn rank<T: Ord>(list: &[T], ties: Ties) -> Ranks {
+    rank_by(list, T::cmp, ties)
+}
+
+/// Calls `f(start, end)` for each group of equal elements `perm[start..end]`,
+/// where `perm` sorts `list`.
+fn for_each_group<T, C: FnMut(&T, &T) -> Ordering, F: FnMut(usize, usize)>(list: &[T], perm: &[usize], mut c: C, mut f: F) {
+    let mut start = 0;
+    while start < perm.len() {
+        let end = start + 1 + upper_bound_by(&perm[start + 1..], &perm[start], Some(0), |&i, &j| c(&list[i], &list[j]));
+        f(start, end);
+        start = end;
+    }
+}
@@ -0,0 +1,53 @@
+//! Ranking the elements of a list by where they come in sorted order.
+
+use std::vec::Vec;
+use rank::{rank, rank_by, Ranks, Ties};
+use test_util::pseudo_random;
+
+/// Test each way of ranking ties against scipy's example.
+#[test]
+fn rankdata() {
+    let list = [0, 2, 3, 2];
+    assert_eq!(rank(&list, Ties::Ordinal), Ranks::Whole(vec![1, 2, 4, 3]));
+    assert_eq!(rank(&list, Ties::Dense), Ranks::Whole(vec![1, 2, 3, 2]));
+    assert_eq!(rank(&list, Ties::Min), Ranks::Whole(vec![1, 2, 4, 2]));
+    assert_eq!(rank(&list, Ties::Max), Ranks::Whole(vec![1, 3, 4, 3]));
+    assert_eq!(rank(&list, Ties::Average), Ranks::Average(vec![1.0, 2.5, 4.0, 2.5]));
+    assert_eq!(rank(&list, Ties::Max).into_f64(), [1.0, 3.0, 4.0, 3.0]);
+    let empty: [u8; 0] = [];
+    assert_eq!(rank(&empty, Ties::Average), Ranks::Average(Vec::new()));
+    assert_eq!(rank(&empty, Ties::Dense), Ranks::Whole(Vec::new()));
+}
+
+/// Test the ranks against counting, for every element, how many others are
+/// less than it and how many are equal.
+#[test]
+fn counted() {
+    for &len in &[1, 2, 10, 100, 1000] {
+        for &distinct in &[1, 3, 101, 10_000] {
+            let list = pseudo_random(len, distinct, 1);
+            let whole = |ties| match rank(&list, ties) {
+                Ranks::Whole(ranks) => ranks,
+                Ranks::Average(_) => panic!("{:?} gave fractional ranks", ties),
+            };
+            let (ordinal, dense, min, max) = (whole(Ties::Ordinal), whole(Ties::Dense), whole(Ties::Min), whole(Ties::Max));
+            let average = match rank_by(&list, |a, b| a.cmp(b), Ties::Average) {
+                Ranks::Average(ranks) => ranks,
+                Ranks::Whole(_) => panic!("average ranks came out whole"),
+            };
+            for (i, x) in list.iter().enumerate() {
+                let less = list.iter().filter(|&y| y < x).count();
+                let equal = list.iter().filter(|&y| y == x).count();
+                let before = list[..i].iter().filter(|&y| y == x).count();
+                let mut values: Vec<usize> = list.iter().cloned().filter(|y| y < x).collect();
+                values.sort_unstable();
+                values.dedup();
+                assert_eq!(ordinal[i], less + before + 1);
+                assert_eq!(dense[i], values.len() + 1);
+                assert_eq!(min[i], less + 1);
+                assert_eq!(max[i], less + equal);
+                assert_eq!(average[i], (2 * less + equal + 1) as f64 / 2.0);
+            }
+        }
+    }
+}
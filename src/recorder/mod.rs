@@ -0,0 +1,233 @@
+//! An observer that writes down what the sort did, so that it can be looked at
+//! afterwards as a tree of merges.
+
+#[cfg(test)]
+mod tests;
+
+use core::fmt::Write;
+use alloc::string::String;
+use alloc::vec::Vec;
+use observer::{MergeKind, SortObserver};
+use sort::Run;
+
+/// One step of a sort, as written down by `SortRecorder`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SortEvent {
+    /// A run that was already in order was found.
+    RunFound { pos: usize, len: usize, reversed: bool },
+    /// A run was lengthened by insertion sort.
+    RunExtended { pos: usize, from: usize, to: usize },
+    /// Two runs were merged.
+    RunsMerged { index: usize, first: Run, second: Run, kind: MergeKind },
+    /// A merge started galloping.
+    GallopEnter,
+    /// A merge stopped galloping.
+    GallopExit,
+}
+
+/// Writes down the runs a sort finds, the merges it does, and when it
+/// gallops.
+///
+/// Pass it to `sort_by_with_observer`, then look through `events`, or turn
+/// the merges into a tree with `to_dot` or `to_json`.
+#[derive(Clone, Debug, Default)]
+pub struct SortRecorder {
+    events: Vec<SortEvent>,
+}
+
+impl SortRecorder {
+    /// Creates a recorder that hasn't seen anything yet.
+    pub fn new() -> SortRecorder {
+        SortRecorder { events: Vec::new() }
+    }
+
+    /// Everything that happened, in order.
+    pub fn events(&self) -> &[SortEvent] {
+        &self.events
+    }
+
+    /// Forgets everything, so the recorder can be used for another sort.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// The merge tree as a Graphviz `digraph`. Each merge points at the two
+    /// runs it merged, and the runs found in the list are the leaves.
+    pub fn to_dot(&self) -> String {
+        let (nodes, _) = self.tree();
+        let mut out = String::from("digraph merges {\n");
+        for (i, node) in nodes.iter().enumerate() {
+            let end = node.pos + node.len;
+            match node.children {
+                Some((first, second)) => {
+                    let _ = writeln!(out, "    n{} [label=\"merge {} {}..{}\\n{} gallops\"];", i, kind_name(node.kind), node.pos, end, node.gallops);
+                    let _ = writeln!(out, "    n{} -> n{};", i, first);
+                    let _ = writeln!(out, "    n{} -> n{};", i, second);
+                }
+                None => {
+                    let _ = write!(out, "    n{} [shape=box, label=\"run {}..{}\\n{} found", i, node.pos, end, node.found);
+                    if node.reversed {
+                        out.push_str(", reversed");
+                    }
+                    out.push_str("\"];\n");
+                }
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// The merge tree as JSON: a list of root nodes, which is just the one
+    /// unless the sort stopped early. A merge looks like
+    /// `{"pos": 0, "len": 100, "merge": "lo", "gallops": 2, "children": [...]}`,
+    /// and a run looks like
+    /// `{"pos": 0, "len": 50, "found": 20, "reversed": false}`.
+    pub fn to_json(&self) -> String {
+        let (nodes, roots) = self.tree();
+        let mut out = String::from("[");
+        for (i, &root) in roots.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_json(&mut out, &nodes, root);
+        }
+        out.push(']');
+        out
+    }
+
+    /// Rebuilds the merge tree from the events. Returns every node, and the
+    /// ones that were never merged into anything, from left to right.
+    fn tree(&self) -> (Vec<Node>, Vec<usize>) {
+        let mut nodes: Vec<Node> = Vec::new();
+        // The nodes that haven't been merged yet, in order of position.
+        let mut roots: Vec<usize> = Vec::new();
+        let mut gallops = 0;
+        for event in &self.events {
+            match *event {
+                SortEvent::RunFound { pos, len, reversed } => {
+                    leaf(&mut nodes, &mut roots, pos, len, len, reversed);
+                }
+                SortEvent::RunExtended { pos, from, to } => {
+                    match roots.iter().position(|&r| nodes[r].pos == pos && nodes[r].children.is_none()) {
+                        Some(r) => nodes[roots[r]].len = to,
+                        None => {
+                            leaf(&mut nodes, &mut roots, pos, to, from, false);
+                        }
+                    }
+                }
+                SortEvent::RunsMerged { first, second, kind, .. } => {
+                    let a = find(&mut nodes, &mut roots, first);
+                    let b = find(&mut nodes, &mut roots, second);
+                    roots.retain(|&r| r != a && r != b);
+                    let id = nodes.len();
+                    nodes.push(Node {
+                        pos: first.pos,
+                        len: first.len + second.len,
+                        found: 0,
+                        reversed: false,
+                        kind,
+                        gallops,
+                        children: Some((a, b)),
+                    });
+                    insert_root(&nodes, &mut roots, id);
+                    gallops = 0;
+                }
+                SortEvent::GallopEnter => gallops += 1,
+                SortEvent::GallopExit => (),
+            }
+        }
+        (nodes, roots)
+    }
+}
+
+impl SortObserver for SortRecorder {
+    fn run_found(&mut self, pos: usize, len: usize, reversed: bool) {
+        self.events.push(SortEvent::RunFound { pos, len, reversed });
+    }
+
+    fn run_extended(&mut self, pos: usize, from: usize, to: usize) {
+        self.events.push(SortEvent::RunExtended { pos, from, to });
+    }
+
+    fn runs_merged(&mut self, index: usize, first: Run, second: Run, kind: MergeKind) {
+        self.events.push(SortEvent::RunsMerged { index, first, second, kind });
+    }
+
+    fn gallop_enter(&mut self) {
+        self.events.push(SortEvent::GallopEnter);
+    }
+
+    fn gallop_exit(&mut self) {
+        self.events.push(SortEvent::GallopExit);
+    }
+}
+
+/// A run or a merge in the merge tree.
+struct Node {
+    pos: usize,
+    len: usize,
+    /// For runs, how long the run was before it was extended.
+    found: usize,
+    reversed: bool,
+    kind: MergeKind,
+    /// How many times the merge started galloping.
+    gallops: usize,
+    children: Option<(usize, usize)>,
+}
+
+/// Adds a run to the tree. Anything it covers is dropped from the roots.
+fn leaf(nodes: &mut Vec<Node>, roots: &mut Vec<usize>, pos: usize, len: usize, found: usize, reversed: bool) -> usize {
+    roots.retain(|&r| nodes[r].pos < pos || nodes[r].pos + nodes[r].len > pos + len);
+    let id = nodes.len();
+    nodes.push(Node {
+        pos,
+        len,
+        found,
+        reversed,
+        kind: MergeKind::InOrder,
+        gallops: 0,
+        children: None,
+    });
+    insert_root(nodes, roots, id);
+    id
+}
+
+/// Finds the node for a run that is about to be merged. `Peeksort` doesn't
+/// report every run it finds, so if there isn't one, it gets made up.
+fn find(nodes: &mut Vec<Node>, roots: &mut Vec<usize>, run: Run) -> usize {
+    match roots.iter().find(|&&r| nodes[r].pos == run.pos && nodes[r].len == run.len) {
+        Some(&r) => r,
+        None => leaf(nodes, roots, run.pos, run.len, run.len, false),
+    }
+}
+
+fn insert_root(nodes: &[Node], roots: &mut Vec<usize>, id: usize) {
+    let at = roots.iter().position(|&r| nodes[r].pos > nodes[id].pos).unwrap_or(roots.len());
+    roots.insert(at, id);
+}
+
+fn kind_name(kind: MergeKind) -> &'static str {
+    match kind {
+        MergeKind::InOrder => "in_order",
+        MergeKind::Lo => "lo",
+        MergeKind::Hi => "hi",
+        MergeKind::InPlace => "in_place",
+    }
+}
+
+fn write_json(out: &mut String, nodes: &[Node], id: usize) {
+    let node = &nodes[id];
+    let _ = write!(out, "{{\"pos\":{},\"len\":{},", node.pos, node.len);
+    match node.children {
+        Some((first, second)) => {
+            let _ = write!(out, "\"merge\":\"{}\",\"gallops\":{},\"children\":[", kind_name(node.kind), node.gallops);
+            write_json(out, nodes, first);
+            out.push(',');
+            write_json(out, nodes, second);
+            out.push_str("]}");
+        }
+        None => {
+            let _ = write!(out, "\"found\":{},\"reversed\":{}}}", node.found, node.reversed);
+        }
+    }
+}
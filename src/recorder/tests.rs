@@ -0,0 +1,85 @@
+//! An observer that writes down what the sort did.
+
+use std::vec::Vec;
+use observer::MergeKind;
+use recorder::{SortEvent, SortRecorder};
+use sort::sort_with_observer;
+use test_util::pseudo_random;
+
+fn two_runs() -> Vec<usize> {
+    let mut list: Vec<usize> = (0..100).map(|i| i * 2).collect();
+    list.extend((0..100).map(|i| i * 2 + 1));
+    list
+}
+
+/// Test the events from merging two interleaved runs.
+#[test]
+fn events() {
+    let mut list = two_runs();
+    let mut recorder = SortRecorder::new();
+    sort_with_observer(&mut list, &mut recorder, |a, b| a.cmp(b));
+    let events = recorder.events();
+    assert_eq!(events[0], SortEvent::RunFound { pos: 0, len: 100, reversed: false });
+    assert_eq!(events[1], SortEvent::RunFound { pos: 100, len: 100, reversed: false });
+    match events[2] {
+        SortEvent::RunsMerged { index, first, second, kind } => {
+            assert_eq!(index, 0);
+            assert_eq!((first.pos, first.len, second.pos, second.len), (0, 100, 100, 100));
+            assert_eq!(kind, MergeKind::Lo);
+        }
+        ref e => panic!("unexpected {:?}", e),
+    }
+    assert_eq!(events.len(), 3);
+    recorder.clear();
+    assert!(recorder.events().is_empty());
+}
+
+/// Test exporting a small merge tree.
+#[test]
+fn dot_and_json() {
+    let mut list = two_runs();
+    let mut recorder = SortRecorder::new();
+    sort_with_observer(&mut list, &mut recorder, |a, b| a.cmp(b));
+    assert_eq!(recorder.to_dot(), "digraph merges {\n    n0 [shape=box, label=\"run 0..100\\n100 found\"];\n    n1 [shape=box, label=\"run 100..200\\n100 found\"];\n    n2 [label=\"merge lo 0..200\\n0 gallops\"];\n    n2 -> n0;\n    n2 -> n1;\n}\n");
+    assert_eq!(recorder.to_json(), "[{\"pos\":0,\"len\":200,\"merge\":\"lo\",\"gallops\":0,\"children\":[{\"pos\":0,\"len\":100,\"found\":100,\"reversed\":false},{\"pos\":100,\"len\":100,\"found\":100,\"reversed\":false}]}]");
+}
+
+/// Test that a bigger sort ends up as a single tree covering the whole list.
+#[test]
+fn one_root() {
+    let len = 10_000;
+    let mut list = pseudo_random(len, 1009, 0);
+    let mut recorder = SortRecorder::new();
+    sort_with_observer(&mut list, &mut recorder, |a, b| a.cmp(b));
+    let json = recorder.to_json();
+    assert!(json.starts_with("[{\"pos\":0,\"len\":10000,\"merge\":"));
+    assert!(json.ends_with("}]"));
+    let dot = recorder.to_dot();
+    let runs = recorder.events().iter().filter(|e| matches!(e, SortEvent::RunFound { .. })).count();
+    assert_eq!(dot.matches("shape=box").count(), runs);
+    assert_eq!(dot.matches(" -> ").count(), 2 * (runs - 1));
+}
+
+/// Test that a short list, which is only insertion sorted, has no tree.
+#[test]
+fn short() {
+    let mut list = vec![3, 2, 1];
+    let mut recorder = SortRecorder::new();
+    sort_with_observer(&mut list, &mut recorder, |a, b| a.cmp(b));
+    assert_eq!(recorder.to_json(), "[]");
+    assert_eq!(recorder.to_dot(), "digraph merges {\n}\n");
+}
+
+/// Test that Peeksort, which doesn't report every run it merges, still ends
+/// up with a single tree.
+#[test]
+fn peeksort() {
+    use std::cmp::Ordering;
+    use policy::Peeksort;
+    use sort::{try_sort_in, RunStack};
+    let len = 10_000;
+    let mut list = pseudo_random(len, 1009, 0);
+    let mut recorder = SortRecorder::new();
+    try_sort_in(&mut list, |a, b| Ok::<Ordering, ()>(a.cmp(b)), &mut Peeksort, &mut RunStack::new(), &mut Vec::new(), &mut recorder, &mut ()).unwrap();
+    assert!(recorder.to_json().starts_with("[{\"pos\":0,\"len\":10000,\"merge\":"));
+}
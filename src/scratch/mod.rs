@@ -0,0 +1,74 @@
+//! Temporary storage for the merge algorithm. Merging two runs needs room for
+//! a copy of the shorter one, and this is where it comes from.
+
+#[cfg(test)]
+mod tests;
+
+use core::mem::MaybeUninit;
+#[cfg(feature = "alloc")]
+use core::cmp::{max, min};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Somewhere to put elements while they are being merged.
+pub trait Scratch<T> {
+    /// The most elements there can ever be room for. Merges that need more
+    /// than this are done in place instead.
+    fn max_len(&self) -> usize;
+
+    /// Returns room for `len` elements, where `len` is no more than
+    /// `max_len()`. Nothing in the returned slice is ever dropped.
+    fn get(&mut self, len: usize) -> &mut [MaybeUninit<T>];
+}
+
+/// A caller-supplied slice is used as scratch space that never grows.
+impl<T> Scratch<T> for [MaybeUninit<T>] {
+    fn max_len(&self) -> usize {
+        self.len()
+    }
+
+    fn get(&mut self, len: usize) -> &mut [MaybeUninit<T>] {
+        &mut self[..len]
+    }
+}
+
+/// A vector is used as scratch space that grows on demand. Its length is always
+/// zero, and everything is put in its spare capacity, so the allocation can be
+/// reused without ever dropping whatever was left in it.
+#[cfg(feature = "alloc")]
+impl<T> Scratch<T> for Vec<T> {
+    fn max_len(&self) -> usize {
+        usize::MAX
+    }
+
+    fn get(&mut self, len: usize) -> &mut [MaybeUninit<T>] {
+        debug_assert!(self.is_empty());
+        self.reserve(len);
+        &mut self.spare_capacity_mut()[..len]
+    }
+}
+
+/// A vector that is never grown past `max` elements.
+#[cfg(feature = "alloc")]
+pub struct Bounded<'a, T: 'a> {
+    pub buf: &'a mut Vec<T>,
+    pub max: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T: 'a> Scratch<T> for Bounded<'a, T> {
+    fn max_len(&self) -> usize {
+        self.max
+    }
+
+    fn get(&mut self, len: usize) -> &mut [MaybeUninit<T>] {
+        debug_assert!(self.buf.is_empty());
+        debug_assert!(len <= self.max);
+        let capacity = self.buf.capacity();
+        if len > capacity {
+            // Grow the same way `reserve` would, but stop at the cap.
+            self.buf.reserve_exact(min(max(len, capacity * 2), self.max));
+        }
+        &mut self.buf.spare_capacity_mut()[..len]
+    }
+}
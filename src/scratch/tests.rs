@@ -0,0 +1,48 @@
+use std::string::String;
+use std::vec::Vec;
+use scratch::{Bounded, Scratch};
+use std::mem::MaybeUninit;
+
+/// Test that a vector grows to fit what is asked of it.
+#[test]
+fn vec_grows() {
+    let mut buf: Vec<String> = Vec::new();
+    assert_eq!(buf.get(0).len(), 0);
+    assert_eq!(buf.get(10).len(), 10);
+    assert!(buf.capacity() >= 10);
+    assert!(buf.is_empty());
+}
+
+/// Test that a vector keeps its allocation when asked for less.
+#[test]
+fn vec_reuses() {
+    let mut buf: Vec<String> = Vec::with_capacity(100);
+    let ptr = buf.as_ptr();
+    assert_eq!(buf.get(50).len(), 50);
+    assert_eq!(buf.as_ptr(), ptr);
+    assert_eq!(buf.capacity(), 100);
+}
+
+/// Test that a slice hands out only as much as it has.
+#[test]
+fn slice_fixed() {
+    let mut buf = [MaybeUninit::<u32>::uninit(); 10];
+    let buf: &mut [MaybeUninit<u32>] = &mut buf;
+    assert_eq!(buf.max_len(), 10);
+    assert_eq!(buf.get(0).len(), 0);
+    assert_eq!(buf.get(10).len(), 10);
+}
+
+/// Test that a bounded vector never grows past its cap.
+#[test]
+fn bounded_capped() {
+    let mut buf: Vec<String> = Vec::new();
+    {
+        let mut bounded = Bounded { buf: &mut buf, max: 100 };
+        assert_eq!(bounded.max_len(), 100);
+        assert_eq!(bounded.get(60).len(), 60);
+        assert_eq!(bounded.get(70).len(), 70);
+        assert_eq!(bounded.get(100).len(), 100);
+    }
+    assert_eq!(buf.capacity(), 100);
+}
@@ -0,0 +1,69 @@
+//! Searching sorted slices, optionally starting from a guess at where the
+//! answer is and galloping outwards from there, as CPython's `gallop_left` and
+//! `gallop_right` do with their hint. The merges here only ever gallop in from
+//! one end of a run, so they have their own search.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::ops::Range;
+use gallop::gallop_from;
+
+/// Returns the first index where `key` could be inserted into `list` and keep
+/// it sorted, which is the index of the first element that isn't less than
+/// `key`.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`,
+/// and `list` must be sorted by it. If `hint` is given, the search starts
+/// there and gallops outwards, so it takes `O(log d)` comparisons when the
+/// answer is `d` away from the hint. Without one, it is a binary search.
+///
+/// ```
+/// let list = [1, 2, 2, 2, 3];
+/// assert_eq!(timsort::lower_bound_by(&list, &2, Some(4), |a, b| a.cmp(b)), 1);
+/// ```
+pub fn lower_bound_by<T, C: FnMut(&T, &T) -> Ordering>(list: &[T], key: &T, hint: Option<usize>, c: C) -> usize {
+    search(list, key, hint, true, c)
+}
+
+/// Returns the last index where `key` could be inserted into `list` and keep
+/// it sorted, which is the index of the first element that is greater than
+/// `key`. See `lower_bound_by` for how `hint` is used.
+pub fn upper_bound_by<T, C: FnMut(&T, &T) -> Ordering>(list: &[T], key: &T, hint: Option<usize>, c: C) -> usize {
+    search(list, key, hint, false, c)
+}
+
+/// Returns the range of elements in `list` that are equal to `key`. It is
+/// empty, and starts where `key` would go, if there aren't any.
+///
+/// The start is found as it is by `lower_bound_by`, and the end by galloping
+/// on from there, so a short range costs hardly more than finding its start.
+pub fn equal_range_by<T, C: FnMut(&T, &T) -> Ordering>(list: &[T], key: &T, hint: Option<usize>, mut c: C) -> Range<usize> {
+    let start = search(list, key, hint, true, &mut c);
+    let end = start + search(&list[start..], key, Some(0), false, &mut c);
+    start..end
+}
+
+/// Like `lower_bound_by`, for types with a total order.
+pub fn lower_bound<T: Ord>(list: &[T], key: &T, hint: Option<usize>) -> usize {
+    lower_bound_by(list, key, hint, T::cmp)
+}
+
+/// Like `upper_bound_by`, for types with a total order.
+pub fn upper_bound<T: Ord>(list: &[T], key: &T, hint: Option<usize>) -> usize {
+    upper_bound_by(list, key, hint, T::cmp)
+}
+
+/// Like `equal_range_by`, for types with a total order.
+pub fn equal_range<T: Ord>(list: &[T], key: &T, hint: Option<usize>) -> Range<usize> {
+    equal_range_by(list, key, hint, T::cmp)
+}
+
+fn search<T, C: FnMut(&T, &T) -> Ordering>(list: &[T], key: &T, hint: Option<usize>, left: bool, mut c: C) -> usize {
+    match gallop_from(key, list, hint, left, |a, b| Ok::<Ordering, Infallible>(c(a, b))) {
+        Ok(i) => i,
+        Err(e) => match e {},
+    }
+}
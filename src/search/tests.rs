@@ -0,0 +1,48 @@
+//! Searching sorted slices, optionally starting from a guess at where the
+//! answer is.
+
+use std::vec::Vec;
+use search::{lower_bound, upper_bound, equal_range, lower_bound_by};
+
+fn lists() -> Vec<Vec<usize>> {
+    let mut lists = Vec::new();
+    for len in 0..40 {
+        lists.push((0..len).map(|i| i / 3 * 2).collect());
+        lists.push((0..len).map(|i| i * i / 50).collect());
+    }
+    lists
+}
+
+/// Test every key and every hint against a linear search.
+#[test]
+fn brute_force() {
+    for list in lists() {
+        let max = list.last().map_or(0, |&x| x + 2);
+        for key in 0..max {
+            let lower = list.iter().position(|&x| x >= key).unwrap_or(list.len());
+            let upper = list.iter().position(|&x| x > key).unwrap_or(list.len());
+            assert_eq!(lower_bound(&list, &key, None), lower);
+            assert_eq!(upper_bound(&list, &key, None), upper);
+            assert_eq!(equal_range(&list, &key, None), lower..upper);
+            for hint in 0..list.len() + 2 {
+                assert_eq!(lower_bound(&list, &key, Some(hint)), lower);
+                assert_eq!(upper_bound(&list, &key, Some(hint)), upper);
+                assert_eq!(equal_range(&list, &key, Some(hint)), lower..upper);
+            }
+        }
+    }
+}
+
+/// Test that a good hint saves comparisons on a long list.
+#[test]
+fn hint_is_cheap() {
+    let list: Vec<usize> = (0..1_000_000).collect();
+    let count = |hint| {
+        let mut comparisons = 0;
+        let i = lower_bound_by(&list, &500_003, hint, |a, b| { comparisons += 1; a.cmp(b) });
+        assert_eq!(i, 500_003);
+        comparisons
+    };
+    assert!(count(Some(500_000)) <= 6);
+    assert!(count(None) >= 19);
+}
@@ -4,18 +4,40 @@
 #[cfg(test)]
 mod tests;
 
-use std::cmp::Ordering;
-use std::cmp::min;
+use core::cmp::Ordering;
+use core::cmp::min;
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ops::Deref;
 use find_run::get_run;
 use insort;
-use merge::merge;
+use merge::{merge, MIN_GALLOP};
+use policy::{MergePolicy, Powersort};
+use scratch::Scratch;
+use observer::SortObserver;
+use moves::{Moves, Offset};
+use check::ComparatorViolation;
+#[cfg(feature = "alloc")]
+use check::CheckError;
+#[cfg(feature = "alloc")]
+use stats::SortStats;
+#[cfg(feature = "alloc")]
+use core::cell::Cell;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use core::mem::size_of;
+#[cfg(feature = "alloc")]
+use indirect::{try_sort_indirect, INDIRECT_SIZE};
 
 /// Minimum run length to merge; anything shorter will be lengthend and
-/// sorted using `insort::sort`.
+/// sorted using `insort::binary_sort`. Lists shorter than this are sorted
+/// with `insort::sort` alone.
 const MIN_MERGE: usize = 64;
 
 /// Compute the actual minimum merge size for a particular list.
-fn calc_min_merge(mut len: usize) -> usize {
+pub fn calc_min_merge(mut len: usize) -> usize {
     if len < MIN_MERGE {
         len
     } else {
@@ -29,127 +51,416 @@ fn calc_min_merge(mut len: usize) -> usize {
 }
 
 /// Represents a known-sorted sublist.
-#[derive(Copy, Clone, Debug)]
-struct Run {
-    pos: usize,
-    len: usize
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Run {
+    /// Where the run starts in the list.
+    pub pos: usize,
+    /// How many elements are in it.
+    pub len: usize,
+}
+
+/// The most runs that can be on the stack at once. Every run but the last is
+/// at least `MIN_MERGE / 2` long. With the `Timsort` policy each run on the
+/// stack is longer than the two above it put together, with `Powersort` each
+/// boundary has a higher power than the one below it, and with `ShiversSort`
+/// each run is at least twice as long as the one above it, so even a list of
+/// `usize::MAX` elements stays well under this.
+const MAX_RUNS: usize = 96;
+
+/// A fixed-size stack of runs waiting to be merged. It lives wherever the
+/// sort is called from, so finding and merging runs never allocates.
+///
+/// Next to each run is a power, for the merge policy to use. The policy can
+/// change the powers, but it only ever gets to read the runs, so it can't
+/// move their boundaries around.
+#[derive(Copy, Clone)]
+pub struct RunStack {
+    runs: [Run; MAX_RUNS],
+    powers: [u32; MAX_RUNS],
+    len: usize,
+}
+
+impl RunStack {
+    pub fn new() -> RunStack {
+        RunStack {
+            runs: [Run{ pos: 0, len: 0 }; MAX_RUNS],
+            powers: [0; MAX_RUNS],
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Pushes a run, with a power of zero.
+    pub fn push(&mut self, run: Run) {
+        self.runs[self.len] = run;
+        self.powers[self.len] = 0;
+        self.len += 1;
+    }
+
+    /// Replaces the run at `n` and the one above it with the two put
+    /// together, which keeps the power of the one above. Returns the two runs.
+    pub fn merge(&mut self, n: usize) -> (Run, Run) {
+        let (run1, run2) = (self.runs[n], self.runs[n + 1]);
+        debug_assert_eq!(run1.pos + run1.len, run2.pos);
+        self.runs[n] = Run{
+            pos: run1.pos,
+            len: run1.len + run2.len,
+        };
+        self.powers[n] = self.powers[n + 1];
+        self.runs.copy_within(n + 2..self.len, n + 1);
+        self.powers.copy_within(n + 2..self.len, n + 1);
+        self.len -= 1;
+        (run1, run2)
+    }
+
+    /// Asks `policy` which runs to merge after a push, or after a merge.
+    pub fn collapse<P: MergePolicy + ?Sized>(&mut self, policy: &mut P, list_len: usize) -> Option<usize> {
+        policy.merge_collapse(&self.runs[..self.len], &mut self.powers[..self.len], list_len)
+    }
+
+    /// Asks `policy` which runs to merge once every run has been pushed.
+    pub fn force_collapse<P: MergePolicy + ?Sized>(&mut self, policy: &mut P, list_len: usize) -> usize {
+        policy.merge_force_collapse(&self.runs[..self.len], list_len)
+    }
+}
+
+impl Deref for RunStack {
+    type Target = [Run];
+    fn deref(&self) -> &[Run] {
+        &self.runs[..self.len]
+    }
 }
 
 /// All the ongoing state of the sort.
-struct SortState<'a, T: 'a, C: Fn(&T, &T) -> Ordering> {
+struct SortState<'a, T: 'a, E, C: FnMut(&T, &T) -> Result<Ordering, E>, P: 'a + MergePolicy + ?Sized, S: 'a + Scratch<T> + ?Sized, O: 'a + SortObserver + ?Sized, M: 'a + Moves + ?Sized> {
     /// The list that is being sorted.
     list: &'a mut [T],
     /// The comparator function. Should return `Ordering::Greater` if the first
     /// argument is goes after the second.
     c: C,
+    /// Decides which runs to merge, and when.
+    policy: &'a mut P,
     /// The list of known-sorted sections of the list that can be merged.
-    /// To keep the size of this list down, this invariant is preserved:
-    ///  - `runs.len < 3 || runs[i-2].len > runs[i-1].len + runs[i].len`
-    ///  - `runs.len < 2 || runs[i-1].len > runs[i].len`
-    runs: Vec<Run>,
+    /// The policy keeps the size of this list down.
+    runs: &'a mut RunStack,
+    /// Where the shorter run goes while two runs are being merged.
+    tmp: &'a mut S,
+    /// How many times in a row one run has to win a merge before we start
+    /// galloping. It goes down when galloping pays off, and up when it
+    /// doesn't, so it is kept from one merge to the next.
+    min_gallop: usize,
+    /// Gets told about everything the sort does.
+    o: &'a mut O,
+    /// Gets told about every element the sort moves.
+    m: &'a mut M,
     /// The current position in the list. When `pos == list.len()`, we can now
     /// merge the last of the runs, and we're done.
     pos: usize,
+    /// In a checked sort, how to turn a `ComparatorViolation` into an error.
+    /// Every merge is then followed by making sure it came out sorted.
+    check: Option<fn(ComparatorViolation) -> E>,
+    _e: PhantomData<E>,
 }
 
-impl<'a, T: 'a, C: Fn(&T, &T) -> Ordering> SortState<'a, T, C> {
+impl<'a, T: 'a, E, C: FnMut(&T, &T) -> Result<Ordering, E>, P: 'a + MergePolicy + ?Sized, S: 'a + Scratch<T> + ?Sized, O: 'a + SortObserver + ?Sized, M: 'a + Moves + ?Sized> SortState<'a, T, E, C, P, S, O, M> {
 
-    fn new(list: &'a mut [T], c: C) -> SortState<'a, T, C> {
+    fn new(list: &'a mut [T], c: C, policy: &'a mut P, runs: &'a mut RunStack, tmp: &'a mut S, o: &'a mut O, m: &'a mut M) -> SortState<'a, T, E, C, P, S, O, M> {
+        runs.clear();
         SortState {
-            list: list,
-            c: c,
-            runs: Vec::new(),
+            list,
+            c,
+            policy,
+            runs,
+            tmp,
+            min_gallop: MIN_GALLOP,
+            o,
+            m,
             pos: 0,
+            check: None,
+            _e: PhantomData,
         }
     }
 
     /// The outer loop. Find runs, and move forward.
-    fn sort(&mut self) {
+    fn sort(&mut self) -> Result<(), E> {
         let list_len = self.list.len();
         // Minimum run size to use merge sort on. Any sorted sections of the
-        // list that are shorter than this are lengthened using `insort::sort`.
+        // list that are shorter than this are lengthened using
+        // `insort::binary_sort`.
         let min_run = calc_min_merge(list_len);
+        if self.policy.top_down() {
+            let first_run_end = self.run_end(0, list_len)?;
+            let last_run_start = self.run_start(0, list_len)?;
+            self.o.run_found(0, first_run_end, false);
+            if last_run_start >= first_run_end {
+                self.o.run_found(last_run_start, list_len - last_run_start, false);
+            }
+            return self.peeksort(0, list_len, first_run_end, last_run_start, min_run);
+        }
         while self.pos < list_len {
             let pos = self.pos;
-            let mut run_len = get_run(self.list.split_at_mut(pos).1, &self.c);
+            let (reversed, mut run_len) = get_run(self.list.split_at_mut(pos).1, &mut self.c, self.o, &mut Offset::new(self.m, pos))?;
+            self.o.run_found(pos, run_len, reversed);
             let run_min_len = min(min_run, list_len - pos);
             if run_len < run_min_len {
+                // The run we found is already sorted, so only insert the
+                // elements after it.
+                self.o.run_extended(pos, run_len, run_min_len);
+                let l = self.list.split_at_mut(pos).1.split_at_mut(run_min_len).0;
+                insort::binary_sort(l, run_len, &mut self.c, self.o, &mut Offset::new(self.m, pos))?;
                 run_len = run_min_len;
-                let l = self.list.split_at_mut(pos).1.split_at_mut(run_len).0;
-                insort::sort(l, &self.c);
             }
             self.runs.push(Run{
-                pos: pos,
+                pos,
                 len: run_len,
             });
             self.pos += run_len;
-            self.merge_collapse();
+            self.merge_collapse()?;
         }
-        self.merge_force_collapse();
-    }
-
-    /// Merge the runs if they're too big.
-    /// Copied almost verbatim from
-    /// http://envisage-project.eu/proving-android-java-and-python-sorting-algorithm-is-broken-and-how-to-fix-it/#sec3.2
-    fn merge_collapse(&mut self) {
-        let runs = &mut self.runs;
-        while runs.len() > 1 {
-            let n = runs.len() - 2;
-            if    (n >= 1 && runs[n - 1].len <= runs[n].len + runs[n + 1].len)
-               || (n >= 2 && runs[n - 2].len <= runs[n].len + runs[n - 1].len) {
-                let (pos1, pos2) = if runs[n - 1].len < runs[n + 1].len {
-                    (n - 1, n)
-                } else {
-                    (n, n + 1)
-                };
-                let (run1, run2) = (runs[pos1], runs[pos2]);
-                debug_assert_eq!(run1.pos + run1.len, run2.pos);
-                runs.remove(pos2);
-                runs[pos1] = Run{
-                    pos: run1.pos,
-                    len: run1.len + run2.len,
-                };
-                let l = self.list.split_at_mut(run1.pos).1;
-                let l = l.split_at_mut(run1.len + run2.len).0;
-                merge(l, run1.len, &self.c);
-            } else {
-                break; // Invariant established.
-            }
+        self.merge_force_collapse()
+    }
+
+    /// Merge the runs until the policy is happy with the stack.
+    fn merge_collapse(&mut self) -> Result<(), E> {
+        while let Some(n) = self.runs.collapse(self.policy, self.list.len()) {
+            self.merge_at(n)?;
         }
+        Ok(())
     }
 
     /// Merge any outstanding runs, at the end.
-    fn merge_force_collapse(&mut self) {
-        let runs = &mut self.runs;
-        while runs.len() > 1 {
-            let n = runs.len() - 2;
-            let (pos1, pos2) = if n > 0 && runs[n - 1].len < runs[n + 1].len {
-                (n - 1, n)
+    fn merge_force_collapse(&mut self) -> Result<(), E> {
+        while self.runs.len() > 1 {
+            let n = self.runs.force_collapse(self.policy, self.list.len());
+            self.merge_at(n)?;
+        }
+        Ok(())
+    }
+
+    /// Merge the run at `n` on the stack with the one above it.
+    fn merge_at(&mut self, n: usize) -> Result<(), E> {
+        let (run1, run2) = self.runs.merge(n);
+        let l = self.list.split_at_mut(run1.pos).1;
+        let l = l.split_at_mut(run1.len + run2.len).0;
+        let kind = merge(l, run1.len, &mut self.c, self.tmp, &mut self.min_gallop, self.o, &mut Offset::new(self.m, run1.pos))?;
+        self.o.runs_merged(n, run1, run2, kind);
+        self.verify(run1.pos, run2.pos + run2.len)
+    }
+
+    /// Peeksort's recursion. `list[lo..e]` and `list[s..hi]` are already
+    /// known to be ascending runs.
+    fn peeksort(&mut self, lo: usize, hi: usize, e: usize, s: usize, min_run: usize) -> Result<(), E> {
+        if hi - lo < 2 || s < e {
+            // The two runs overlap, so the whole thing is sorted.
+            return Ok(());
+        }
+        if hi - lo < min_run {
+            self.o.run_extended(lo, e - lo, hi - lo);
+            return insort::binary_sort(&mut self.list[lo..hi], e - lo, &mut self.c, self.o, &mut Offset::new(self.m, lo));
+        }
+        let m = lo + (hi - lo) / 2;
+        let split = if m < e {
+            self.peeksort(e, hi, e + 1, s, min_run)?;
+            e
+        } else if m >= s {
+            self.peeksort(lo, s, e, s - 1, min_run)?;
+            s
+        } else {
+            // Find the run around the middle, and split at whichever of its
+            // ends is closer.
+            let i = self.run_start(lo, m + 1)?;
+            let j = self.run_end(m, hi)?;
+            if i == lo && j == hi {
+                return Ok(());
+            }
+            self.o.run_found(i, j - i, false);
+            if j == hi || (i != lo && m - i < j - m) {
+                self.peeksort(lo, i, e, i - 1, min_run)?;
+                self.peeksort(i, hi, j, s, min_run)?;
+                i
             } else {
-                (n, n + 1)
-            };
-            let (run1, run2) = (runs[pos1], runs[pos2]);
-            debug_assert_eq!(run1.len, run2.pos);
-            runs.remove(pos2);
-            runs[pos1] = Run{
-                pos: run1.pos,
-                len: run1.len + run2.len,
-            };
-            let l = self.list.split_at_mut(run1.pos).1;
-            let l = l.split_at_mut(run1.len + run2.len).0;
-            merge(l, run1.len, &self.c);
+                self.peeksort(lo, j, e, i, min_run)?;
+                self.peeksort(j, hi, j + 1, s, min_run)?;
+                j
+            }
+        };
+        let l = &mut self.list[lo..hi];
+        let kind = merge(l, split - lo, &mut self.c, self.tmp, &mut self.min_gallop, self.o, &mut Offset::new(self.m, lo))?;
+        let first = Run{ pos: lo, len: split - lo };
+        let second = Run{ pos: split, len: hi - split };
+        self.o.runs_merged(0, first, second, kind);
+        self.verify(lo, hi)
+    }
+
+    /// In a checked sort, makes sure `list[start..end]` is sorted.
+    fn verify(&mut self, start: usize, end: usize) -> Result<(), E> {
+        match self.check {
+            Some(violation) => verify(&self.list[start..end], start, &mut self.c, violation),
+            None => Ok(()),
+        }
+    }
+
+    /// Finds the end of the ascending run that starts at `start`, looking no
+    /// further than `end`.
+    fn run_end(&mut self, start: usize, end: usize) -> Result<usize, E> {
+        let mut pos = start + 1;
+        while pos < end && (self.c)(&self.list[pos], &self.list[pos - 1])? != Ordering::Less {
+            pos += 1;
         }
+        Ok(pos)
+    }
+
+    /// Finds the start of the ascending run that ends at `end`, looking no
+    /// further back than `start`.
+    fn run_start(&mut self, start: usize, end: usize) -> Result<usize, E> {
+        let mut pos = end - 1;
+        while pos > start && (self.c)(&self.list[pos], &self.list[pos - 1])? != Ordering::Less {
+            pos -= 1;
+        }
+        Ok(pos)
     }
 }
 
 /// Sorts the list using merge sort.
 ///
 /// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
-pub fn sort<T, C: Fn(&T, &T) -> Ordering>(list: &mut [T], c: C) {
+/// Elements of at least `INDIRECT_SIZE` bytes are sorted through their
+/// indices instead, as `sort_by_indirect` does. Not every entry point does
+/// that; `INDIRECT_SIZE` lists the ones that do.
+#[cfg(feature = "alloc")]
+pub fn sort<T, C: FnMut(&T, &T) -> Ordering>(list: &mut [T], mut c: C) {
+    match try_sort(list, |a, b| Ok::<Ordering, Infallible>(c(a, b))) {
+        Ok(()) => (),
+        Err(e) => match e {},
+    }
+}
+
+/// Sorts the list using merge sort, with a comparator that can fail.
+///
+/// `c(a, b)` should return `Ok(std::cmp::Ordering::Greater)` when `a` is greater than `b`.
+/// The first error returned by `c` stops the sort and is passed back to the
+/// caller. The list is then left unsorted, but it still holds every one of its
+/// original elements exactly once.
+///
+/// Elements of at least `INDIRECT_SIZE` bytes are sorted through their
+/// indices instead, as they are by `sort_by`.
+#[cfg(feature = "alloc")]
+pub fn try_sort<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>>(list: &mut [T], c: C) -> Result<(), E> {
+    if size_of::<T>() >= INDIRECT_SIZE {
+        return try_sort_indirect(list, c);
+    }
+    try_sort_in(list, c, &mut Powersort, &mut RunStack::new(), &mut Vec::new(), &mut (), &mut ())
+}
+
+/// Sorts the list using merge sort, and counts what it took.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+#[cfg(feature = "alloc")]
+pub fn sort_with_stats<T, C: FnMut(&T, &T) -> Ordering>(list: &mut [T], mut c: C) -> SortStats {
+    let mut stats = SortStats::default();
+    let comparisons = Cell::new(0);
+    sort_with_observer(list, &mut stats, |a, b| {
+        comparisons.set(comparisons.get() + 1);
+        c(a, b)
+    });
+    stats.comparisons = comparisons.get();
+    stats
+}
+
+/// Sorts the list using merge sort, telling `o` about each step as it goes.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+#[cfg(feature = "alloc")]
+pub fn sort_with_observer<T, O: SortObserver + ?Sized, C: FnMut(&T, &T) -> Ordering>(list: &mut [T], o: &mut O, mut c: C) {
+    match try_sort_in(list, |a, b| Ok::<Ordering, Infallible>(c(a, b)), &mut Powersort, &mut RunStack::new(), &mut Vec::new(), o, &mut ()) {
+        Ok(()) => (),
+        Err(e) => match e {},
+    }
+}
+
+/// Sorts the list using merge sort, and makes sure the comparator doesn't
+/// contradict itself while doing it.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+/// After every merge, the merged run is checked to be sorted, which takes about
+/// twice as many comparisons as `sort_by`. The first sign that `c` isn't a
+/// total order stops the sort, leaving the list unsorted but with every
+/// element still in it, and is returned. The other sorts never check, even
+/// in debug builds.
+#[cfg(feature = "alloc")]
+pub fn sort_checked<T, C: FnMut(&T, &T) -> Ordering>(list: &mut [T], mut c: C) -> Result<(), ComparatorViolation> {
+    match try_sort_checked(list, |a, b| Ok::<Ordering, Infallible>(c(a, b))) {
+        Ok(()) => Ok(()),
+        Err(CheckError::Comparator(e)) => match e {},
+        Err(CheckError::Violation(v)) => Err(v),
+    }
+}
+
+/// Sorts the list using merge sort, with a comparator that can fail, and makes
+/// sure it doesn't contradict itself. See `sort_checked`.
+#[cfg(feature = "alloc")]
+pub fn try_sort_checked<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>>(list: &mut [T], mut c: C) -> Result<(), CheckError<E>> {
+    let mut c = |a: &T, b: &T| c(a, b).map_err(CheckError::Comparator);
     if list.len() < MIN_MERGE {
-        insort::sort(list, c);
+        insort::sort(&mut *list, &mut c, &mut (), &mut ())?;
+        verify(list, 0, &mut c, CheckError::Violation)
     } else {
-        let mut sort_state = SortState::new(list, c);
-        sort_state.sort();
+        let (mut policy, mut runs, mut tmp, mut o, mut m) = (Powersort, RunStack::new(), Vec::new(), (), ());
+        let mut sort_state = SortState::new(list, c, &mut policy, &mut runs, &mut tmp, &mut o, &mut m);
+        sort_state.check = Some(CheckError::Violation);
+        sort_state.sort()
     }
 }
+
+/// Makes sure `list` is sorted, where `list` starts at `offset` in the list
+/// being sorted.
+fn verify<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>>(list: &[T], offset: usize, mut c: C, violation: fn(ComparatorViolation) -> E) -> Result<(), E> {
+    for i in 1..list.len() {
+        if c(&list[i], &list[i - 1])? == Ordering::Less {
+            return Err(violation(ComparatorViolation {
+                first: offset + i - 1,
+                second: offset + i,
+                merged: offset..offset + list.len(),
+            }));
+        }
+    }
+    Ok(())
+}
+
+/// Sorts the list using merge sort, merging runs in the order `policy` picks,
+/// keeping track of them in `runs` and merging them through `tmp`. Each step
+/// is reported to `o`, and each move to `m`.
+pub fn try_sort_in<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>, P: MergePolicy + ?Sized, S: Scratch<T> + ?Sized, O: SortObserver + ?Sized, M: Moves + ?Sized>(list: &mut [T], c: C, policy: &mut P, runs: &mut RunStack, tmp: &mut S, o: &mut O, m: &mut M) -> Result<(), E> {
+    if list.len() < MIN_MERGE {
+        insort::sort(list, c, o, m)
+    } else {
+        let mut sort_state = SortState::new(list, c, policy, runs, tmp, o, m);
+        sort_state.sort()
+    }
+}
+
+/// Sorts the list using merge sort, without allocating.
+///
+/// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+/// `buf` is used to merge runs. If it has room for at least half as many
+/// elements as `list`, every merge goes through it; merges that don't fit are
+/// done in place instead, which is slower but still stable. Runs are kept
+/// track of in a fixed-size stack, so nothing at all is allocated on the heap.
+pub fn sort_with_buffer<T, C: FnMut(&T, &T) -> Ordering>(list: &mut [T], buf: &mut [MaybeUninit<T>], mut c: C) {
+    match try_sort_with_buffer(list, buf, |a, b| Ok::<Ordering, Infallible>(c(a, b))) {
+        Ok(()) => (),
+        Err(e) => match e {},
+    }
+}
+
+/// Sorts the list using merge sort, without allocating, with a comparator that
+/// can fail.
+///
+/// See `try_sort` for what happens when it does, and `sort_with_buffer` for
+/// how big `buf` should be.
+pub fn try_sort_with_buffer<T, E, C: FnMut(&T, &T) -> Result<Ordering, E>>(list: &mut [T], buf: &mut [MaybeUninit<T>], c: C) -> Result<(), E> {
+    try_sort_in(list, c, &mut Powersort, &mut RunStack::new(), buf, &mut (), &mut ())
+}
@@ -1,14 +1,18 @@
 //! The top sorting algorithm; that is, the modified merge sort we keep
 //! talking about.
 
+use std::vec::Vec;
 use sort as timsort;
+use policy::Powersort;
+use stats::SortStats;
+use test_util::pseudo_random;
 
 /// Test the sort implementation with an empty list
 #[test]
 fn empty() {
     let mut list: Vec<u32> = vec![];
     sort(&mut list);
-    assert!(list.len() == 0);
+    assert!(list.is_empty());
 }
 
 /// Test the sort implementation with a single-element list
@@ -62,18 +66,18 @@ fn stable_npow2() {
     struct Item {
         key1: usize,
         key2: usize,
-    };
+    }
     let mut list: Vec<Item> = (0..len).map(|_| {
         key1 += 1;
         key1 %= 5;
         key2 += 1;
         Item {
-            key1: key1,
-            key2: key2,
+            key1,
+            key2,
         }
     }).collect();
     timsort::sort(&mut list, |a, b| a.key1.cmp(&b.key1));
-    for i in (0 .. (len - 1)) {
+    for i in 0 .. (len - 1) {
         assert!(list[i].key1 <= list[i + 1].key1);
         if list[i].key1 == list[i + 1].key1 {
             assert!(list[i].key2 <= list[i + 1].key2);
@@ -90,18 +94,18 @@ fn stable() {
     struct Item {
         key1: usize,
         key2: usize,
-    };
+    }
     let mut list: Vec<Item> = (0..len).map(|_| {
         key1 += 1;
         key1 %= 5;
         key2 += 1;
         Item {
-            key1: key1,
-            key2: key2,
+            key1,
+            key2,
         }
     }).collect();
     timsort::sort(&mut list, |a, b| a.key1.cmp(&b.key1));
-    for i in (0 .. (len - 1)) {
+    for i in 0 .. (len - 1) {
         assert!(list[i].key1 <= list[i + 1].key1);
         if list[i].key1 == list[i + 1].key1 {
             assert!(list[i].key2 <= list[i + 1].key2);
@@ -109,9 +113,101 @@ fn stable() {
     }
 }
 
+/// Make sure a comparator error stops the sort without losing elements.
+#[test]
+fn try_sort_error() {
+    use std::cell::Cell;
+    let len = 1000;
+    let original = pseudo_random(len, 1009, 0);
+    for fail_at in [0, 1, 50, 500, 5000] {
+        let mut list = original.clone();
+        let calls = Cell::new(0);
+        let result = timsort::try_sort(&mut list, |a, b| {
+            calls.set(calls.get() + 1);
+            if calls.get() > fail_at {
+                Err(calls.get())
+            } else {
+                Ok(a.cmp(b))
+            }
+        });
+        assert_eq!(result, Err(fail_at + 1));
+        let mut expected = original.clone();
+        expected.sort();
+        list.sort();
+        assert_eq!(list, expected);
+    }
+}
+
+/// Make sure a comparator that never fails sorts normally.
+#[test]
+fn try_sort_ok() {
+    let mut list = pseudo_random(1000, 1009, 0);
+    let result: Result<(), ()> = timsort::try_sort(&mut list, |a, b| Ok(a.cmp(b)));
+    assert_eq!(result, Ok(()));
+    for i in 0..(list.len() - 1) {
+        assert!(list[i] <= list[i + 1]);
+    }
+}
+
+/// Make sure a comparator that mutates its environment can be used.
+#[test]
+fn fnmut_comparator() {
+    let mut list = pseudo_random(1000, 1009, 0);
+    let mut calls = 0;
+    timsort::sort(&mut list, |a, b| {
+        calls += 1;
+        a.cmp(b)
+    });
+    assert!(calls >= list.len() - 1);
+    for i in 0..(list.len() - 1) {
+        assert!(list[i] <= list[i + 1]);
+    }
+}
+
+/// Test sorting with a caller-supplied buffer of the smallest allowed size.
+#[test]
+fn with_buffer() {
+    use std::mem::MaybeUninit;
+    for &len in &[0, 1, 63, 64, 65, 1000, 1001] {
+        let mut list = pseudo_random(len, 1009, 0);
+        let mut buf: Vec<MaybeUninit<usize>> = (0..(len / 2)).map(|_| MaybeUninit::uninit()).collect();
+        timsort::sort_with_buffer(&mut list, &mut buf, |a, b| a.cmp(b));
+        for i in 1..list.len() {
+            assert!(list[i - 1] <= list[i]);
+        }
+    }
+}
+
+/// Test sorting with buffers too small for some or all of the merges.
+#[test]
+fn with_buffer_too_small() {
+    use std::mem::MaybeUninit;
+    for &buf_len in &[0, 1, 10, 100, 499] {
+        let len = 1000;
+        let mut key1: usize = 0;
+        let mut list: Vec<(usize, usize)> = (0..len).map(|i| {
+            key1 = (key1 + 7919) % 13;
+            (key1, i)
+        }).collect();
+        let mut buf: Vec<MaybeUninit<(usize, usize)>> = (0..buf_len).map(|_| MaybeUninit::uninit()).collect();
+        timsort::sort_with_buffer(&mut list, &mut buf, |a, b| a.0.cmp(&b.0));
+        for i in 1..list.len() {
+            assert!(list[i - 1].0 <= list[i].0);
+            if list[i - 1].0 == list[i].0 {
+                assert!(list[i - 1].1 < list[i].1);
+            }
+        }
+    }
+}
+
 /// Sort implementation convenience used for tests.
 pub fn sort<T: Ord>(list: &mut[T]) {
-    let mut sort_state = timsort::SortState::new(list, |a, b| a.cmp(b) );
-    sort_state.sort();
+    let mut policy = Powersort;
+    let mut runs = timsort::RunStack::new();
+    let mut tmp = Vec::new();
+    let mut stats = SortStats::default();
+    let mut moves = ();
+    let mut sort_state = timsort::SortState::new(list, |a, b| Ok::<_, ()>(a.cmp(b)), &mut policy, &mut runs, &mut tmp, &mut stats, &mut moves);
+    sort_state.sort().unwrap();
 }
 
@@ -0,0 +1,141 @@
+//! A reusable sorter, for sorting lots of lists one after another without
+//! allocating new working storage for each one.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use alloc::vec::Vec;
+use policy::{MergePolicy, Powersort};
+use scratch::Bounded;
+use sort::{try_sort_in, RunStack};
+use float::nan_last_cmp;
+
+/// Sorts lists, keeping its merge buffer and run stack between calls.
+///
+/// Each merge needs room for a copy of the shorter run being merged. A
+/// `TimSorter` keeps that room around after the sort is done, so sorting many
+/// lists of similar length only allocates once.
+///
+/// The merge buffer can also be capped with `set_max_scratch`. Merges that
+/// need more room than that are done in place instead, by rotating pieces of
+/// the runs into position, which is slower but still stable.
+///
+/// Runs are merged in the order picked by a `MergePolicy`, which is
+/// `Powersort` unless another one is given to `with_policy`.
+pub struct TimSorter<T, P = Powersort> {
+    buf: Vec<T>,
+    runs: RunStack,
+    max_scratch: usize,
+    policy: P,
+}
+
+impl<T> TimSorter<T> {
+    /// Creates a sorter that hasn't allocated anything yet.
+    pub fn new() -> TimSorter<T> {
+        TimSorter::with_capacity(0)
+    }
+
+    /// Creates a sorter with room to merge runs of up to `capacity` elements.
+    /// That is enough to sort a list twice that long without allocating.
+    pub fn with_capacity(capacity: usize) -> TimSorter<T> {
+        let mut sorter = TimSorter::with_policy(Powersort);
+        sorter.buf.reserve_exact(capacity);
+        sorter
+    }
+
+    /// Creates a sorter whose merge buffer never grows past `max_scratch` elements.
+    pub fn with_max_scratch(max_scratch: usize) -> TimSorter<T> {
+        let mut sorter = TimSorter::new();
+        sorter.set_max_scratch(max_scratch);
+        sorter
+    }
+}
+
+impl<T, P: MergePolicy> TimSorter<T, P> {
+    /// Creates a sorter that merges runs in the order `policy` picks.
+    pub fn with_policy(policy: P) -> TimSorter<T, P> {
+        TimSorter {
+            buf: Vec::new(),
+            runs: RunStack::new(),
+            max_scratch: usize::MAX,
+            policy,
+        }
+    }
+
+    /// The policy that picks which runs to merge.
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    /// The policy that picks which runs to merge, for changing its settings.
+    pub fn policy_mut(&mut self) -> &mut P {
+        &mut self.policy
+    }
+
+    /// The most elements the merge buffer will ever be grown to hold.
+    pub fn max_scratch(&self) -> usize {
+        self.max_scratch
+    }
+
+    /// Stops the merge buffer from growing past `max_scratch` elements. If it
+    /// is already bigger than that, it is shrunk.
+    pub fn set_max_scratch(&mut self, max_scratch: usize) {
+        self.max_scratch = max_scratch;
+        if self.buf.capacity() > max_scratch {
+            self.buf.shrink_to(max_scratch);
+        }
+    }
+
+    /// The number of elements the merge buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Frees as much of the merge buffer as possible.
+    pub fn shrink_to_fit(&mut self) {
+        self.buf.shrink_to_fit();
+    }
+
+    /// Frees the merge buffer down to `min_capacity` elements.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.buf.shrink_to(min_capacity);
+    }
+
+    /// Sorts the list using merge sort.
+    ///
+    /// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+    pub fn sort_by<C: FnMut(&T, &T) -> Ordering>(&mut self, list: &mut [T], mut c: C) {
+        match self.try_sort_by(list, |a, b| Ok::<Ordering, Infallible>(c(a, b))) {
+            Ok(()) => (),
+            Err(e) => match e {},
+        }
+    }
+
+    /// Sorts the list using merge sort, with a comparator that can fail.
+    ///
+    /// See the crate-level [`try_sort_by`](crate::try_sort_by) for what happens
+    /// when it does.
+    pub fn try_sort_by<E, C: FnMut(&T, &T) -> Result<Ordering, E>>(&mut self, list: &mut [T], c: C) -> Result<(), E> {
+        let mut tmp = Bounded {
+            buf: &mut self.buf,
+            max: self.max_scratch,
+        };
+        try_sort_in(list, c, &mut self.policy, &mut self.runs, &mut tmp, &mut (), &mut ())
+    }
+}
+
+impl<T: PartialOrd, P: MergePolicy> TimSorter<T, P> {
+    /// Sorts the list in ascending order. Values that can't be compared with
+    /// themselves, like NaN, go at the end, as they do for `timsort::sort`.
+    pub fn sort(&mut self, list: &mut [T]) {
+        self.sort_by(list, nan_last_cmp)
+    }
+}
+
+impl<T> Default for TimSorter<T> {
+    fn default() -> TimSorter<T> {
+        TimSorter::new()
+    }
+}
@@ -0,0 +1,95 @@
+use sorter::TimSorter;
+use test_util::{check_sorted, pseudo_random, tagged};
+
+/// Test sorting several lists with one sorter.
+#[test]
+fn reuse() {
+    let mut sorter = TimSorter::new();
+    for seed in 0..10 {
+        let mut list = pseudo_random(1000, 1009, seed);
+        sorter.sort(&mut list);
+        for i in 0..(list.len() - 1) {
+            assert!(list[i] <= list[i + 1]);
+        }
+    }
+}
+
+/// Test that the merge buffer is kept between sorts, and not regrown.
+#[test]
+fn capacity_is_kept() {
+    let mut sorter = TimSorter::new();
+    assert_eq!(sorter.capacity(), 0);
+    let mut list = pseudo_random(1000, 1009, 0);
+    sorter.sort(&mut list);
+    let capacity = sorter.capacity();
+    assert!(capacity > 0);
+    assert!(capacity <= 1000);
+    for seed in 1..10 {
+        let mut list = pseudo_random(1000, 1009, seed);
+        sorter.sort(&mut list);
+        assert_eq!(sorter.capacity(), capacity);
+    }
+}
+
+/// Test that a sorter with enough capacity never grows.
+#[test]
+fn with_capacity() {
+    let mut sorter = TimSorter::with_capacity(500);
+    let mut list = pseudo_random(1000, 1009, 0);
+    sorter.sort(&mut list);
+    assert_eq!(sorter.capacity(), 500);
+}
+
+/// Test giving back the merge buffer.
+#[test]
+fn shrink() {
+    let mut sorter = TimSorter::with_capacity(500);
+    sorter.shrink_to(100);
+    assert!(sorter.capacity() >= 100);
+    assert!(sorter.capacity() < 500);
+    sorter.shrink_to_fit();
+    assert_eq!(sorter.capacity(), 0);
+    let mut list = pseudo_random(1000, 1009, 0);
+    sorter.sort(&mut list);
+    for i in 0..(list.len() - 1) {
+        assert!(list[i] <= list[i + 1]);
+    }
+}
+
+/// Make sure a sorter still works after the comparator fails part way.
+#[test]
+fn reuse_after_error() {
+    let mut sorter = TimSorter::new();
+    let mut list = pseudo_random(1000, 1009, 0);
+    let mut calls = 0;
+    let result = sorter.try_sort_by(&mut list, |a, b| {
+        calls += 1;
+        if calls > 2000 { Err(()) } else { Ok(a.cmp(b)) }
+    });
+    assert_eq!(result, Err(()));
+    sorter.sort_by(&mut list, |a, b| a.cmp(b));
+    for i in 0..(list.len() - 1) {
+        assert!(list[i] <= list[i + 1]);
+    }
+}
+
+/// Test that a capped sorter stays under its cap, and still sorts stably.
+#[test]
+fn max_scratch() {
+    for &max in &[0, 1, 16, 100, 499] {
+        let mut sorter = TimSorter::with_max_scratch(max);
+        assert_eq!(sorter.max_scratch(), max);
+        let mut list = tagged(pseudo_random(1000, 17, 0));
+        sorter.sort_by(&mut list, |a, b| a.0.cmp(&b.0));
+        assert!(sorter.capacity() <= max);
+        check_sorted(&list);
+    }
+}
+
+/// Test that capping a sorter gives back memory it already had.
+#[test]
+fn set_max_scratch_shrinks() {
+    let mut sorter: TimSorter<usize> = TimSorter::with_capacity(500);
+    sorter.set_max_scratch(100);
+    assert!(sorter.capacity() <= 100);
+}
@@ -0,0 +1,95 @@
+//! Counting what the sort does: how many runs it found, how it merged them,
+//! and how much it had to move things around to do it.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::max;
+use observer::SortObserver;
+
+/// What a sort did, as returned by `sort_by_with_stats`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SortStats {
+    /// How many times the comparator was called.
+    pub comparisons: usize,
+    /// How many runs that were already in order were found.
+    pub natural_runs: usize,
+    /// How long those runs were. `run_lengths[i]` counts the runs with at
+    /// least `2^i` and less than `2^(i + 1)` elements.
+    pub run_lengths: [usize; usize::BITS as usize],
+    /// How many runs were too short, and were lengthened with insertion sort.
+    pub runs_extended: usize,
+    /// How many merges went from low to high, copying out the first run.
+    pub merges_lo: usize,
+    /// How many merges went from high to low, copying out the second run.
+    pub merges_hi: usize,
+    /// How many times a merge started galloping.
+    pub gallop_entries: usize,
+    /// How many times a merge stopped galloping because it wasn't paying off.
+    pub gallop_exits: usize,
+    /// How many elements were moved by galloping, instead of one at a time.
+    pub elements_galloped: usize,
+    /// The most scratch space any one merge needed, in elements.
+    pub peak_scratch: usize,
+    /// How many times an element was moved, in total.
+    pub moves: usize,
+}
+
+impl Default for SortStats {
+    fn default() -> SortStats {
+        SortStats {
+            comparisons: 0,
+            natural_runs: 0,
+            run_lengths: [0; usize::BITS as usize],
+            runs_extended: 0,
+            merges_lo: 0,
+            merges_hi: 0,
+            gallop_entries: 0,
+            gallop_exits: 0,
+            elements_galloped: 0,
+            peak_scratch: 0,
+            moves: 0,
+        }
+    }
+}
+
+impl SortObserver for SortStats {
+    fn run_found(&mut self, _pos: usize, len: usize, _reversed: bool) {
+        self.natural_runs += 1;
+        if len > 0 {
+            self.run_lengths[(usize::BITS - 1 - len.leading_zeros()) as usize] += 1;
+        }
+    }
+
+    fn run_extended(&mut self, _pos: usize, _from: usize, _to: usize) {
+        self.runs_extended += 1;
+    }
+
+    fn merge_lo(&mut self, _first_len: usize, _second_len: usize) {
+        self.merges_lo += 1;
+    }
+
+    fn merge_hi(&mut self, _first_len: usize, _second_len: usize) {
+        self.merges_hi += 1;
+    }
+
+    fn gallop_enter(&mut self) {
+        self.gallop_entries += 1;
+    }
+
+    fn gallop_exit(&mut self) {
+        self.gallop_exits += 1;
+    }
+
+    fn galloped(&mut self, count: usize) {
+        self.elements_galloped += count;
+    }
+
+    fn scratch(&mut self, len: usize) {
+        self.peak_scratch = max(self.peak_scratch, len);
+    }
+
+    fn moved(&mut self, count: usize) {
+        self.moves += count;
+    }
+}
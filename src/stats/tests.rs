@@ -0,0 +1,72 @@
+//! Counting what the sort does.
+
+use std::vec::Vec;
+use sort::sort_with_stats;
+use test_util::pseudo_random;
+
+/// Test that a short list is only insertion sorted.
+#[test]
+fn short() {
+    let mut list = vec![3, 1, 2];
+    let stats = sort_with_stats(&mut list, |a, b| a.cmp(b));
+    assert_eq!(list, vec![1, 2, 3]);
+    assert!(stats.comparisons > 0);
+    assert_eq!(stats.natural_runs, 0);
+    assert_eq!(stats.merges_lo + stats.merges_hi, 0);
+    assert_eq!(stats.peak_scratch, 0);
+    assert!(stats.moves > 0);
+}
+
+/// Test that a sorted list is found to be one run, and nothing is moved.
+#[test]
+fn sorted() {
+    let mut list: Vec<usize> = (0..1000).collect();
+    let stats = sort_with_stats(&mut list, |a, b| a.cmp(b));
+    assert_eq!(stats.comparisons, 999);
+    assert_eq!(stats.natural_runs, 1);
+    assert_eq!(stats.run_lengths[9], 1);
+    assert_eq!(stats.runs_extended, 0);
+    assert_eq!(stats.merges_lo + stats.merges_hi, 0);
+    assert_eq!(stats.moves, 0);
+}
+
+/// Test that a reversed list is reversed in place.
+#[test]
+fn reversed() {
+    let mut list: Vec<usize> = (0..1000).rev().collect();
+    let stats = sort_with_stats(&mut list, |a, b| a.cmp(b));
+    assert_eq!(stats.natural_runs, 1);
+    assert_eq!(stats.moves, 1000);
+}
+
+/// Test that two interleaved halves are merged once, galloping through
+/// the long stretches.
+#[test]
+fn two_runs() {
+    let mut list: Vec<usize> = (0..1000).map(|i| i * 2).collect();
+    list.extend((0..100).map(|i| i * 2 + 1));
+    list.extend((200..1000).map(|i| i * 2 + 1));
+    let stats = sort_with_stats(&mut list, |a, b| a.cmp(b));
+    assert!(list.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(stats.natural_runs, 2);
+    assert_eq!(stats.run_lengths[9] + stats.run_lengths[10], 2);
+    assert_eq!(stats.merges_lo + stats.merges_hi, 1);
+    assert!(stats.gallop_entries > 0);
+    assert!(stats.elements_galloped > 0);
+    assert!(stats.peak_scratch > 0);
+    assert!(stats.peak_scratch <= 900);
+    assert!(stats.moves >= 2 * stats.peak_scratch);
+}
+
+/// Test that short runs are counted, and extended.
+#[test]
+fn extended() {
+    let len = 10_000;
+    let mut list = pseudo_random(len, 1009, 0);
+    let stats = sort_with_stats(&mut list, |a, b| a.cmp(b));
+    assert!(list.windows(2).all(|w| w[0] <= w[1]));
+    assert!(stats.runs_extended > 0);
+    assert_eq!(stats.run_lengths.iter().sum::<usize>(), stats.natural_runs);
+    assert!(stats.gallop_exits <= stats.gallop_entries);
+    assert!(stats.merges_lo + stats.merges_hi >= stats.runs_extended - 1);
+}
@@ -0,0 +1,177 @@
+//! A sorter that is fed one element at a time, and does most of its merging
+//! while it is still being fed.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::{min, Ordering};
+use core::convert::Infallible;
+use alloc::vec::Vec;
+use insort;
+use merge::{merge, MIN_GALLOP};
+use policy::Timsort;
+use sort::{Run, RunStack};
+
+/// How long each run has to be before it goes on the stack. The length of
+/// the whole list isn't known in advance, so this can't be tuned to it the
+/// way the minimum run length for `sort_by` is.
+const MIN_RUN: usize = 32;
+
+/// Sorts elements as they arrive.
+///
+/// Every element passed to `push` or `extend` is added to the end of a
+/// buffer. As soon as a run at the end of the buffer is known to be over, it
+/// is put on a run stack, and merged with the runs before it whenever the
+/// stack stops following the TimSort rule (see `Timsort`). By the time
+/// `finish` is called, only the runs still on the stack are left to merge.
+///
+/// ```
+/// let mut sorter = timsort::StreamingSorter::new(|a: &u32, b: &u32| a.cmp(b));
+/// sorter.push(3);
+/// sorter.extend(vec![1, 4, 1, 5]);
+/// assert_eq!(sorter.finish(), vec![1, 1, 3, 4, 5]);
+/// ```
+pub struct StreamingSorter<T, C: FnMut(&T, &T) -> Ordering> {
+    buf: Vec<T>,
+    c: C,
+    runs: RunStack,
+    tmp: Vec<T>,
+    min_gallop: usize,
+    /// Where the run that is still being found starts.
+    pos: usize,
+    /// How far into the buffer that run is known to go.
+    run_end: usize,
+    /// Whether that run is descending.
+    descending: bool,
+    /// Whether the element at `run_end` has already been found not to carry
+    /// the run on, so it doesn't need comparing again.
+    run_closed: bool,
+}
+
+impl<T, C: FnMut(&T, &T) -> Ordering> StreamingSorter<T, C> {
+    /// Creates a sorter that orders elements using `c`.
+    ///
+    /// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+    pub fn new(c: C) -> StreamingSorter<T, C> {
+        StreamingSorter {
+            buf: Vec::new(),
+            c,
+            runs: RunStack::new(),
+            tmp: Vec::new(),
+            min_gallop: MIN_GALLOP,
+            pos: 0,
+            run_end: 0,
+            descending: false,
+            run_closed: false,
+        }
+    }
+
+    /// How many elements have been pushed so far.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Whether nothing has been pushed yet.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Adds one element.
+    pub fn push(&mut self, x: T) {
+        self.buf.push(x);
+        self.collapse(false);
+    }
+
+    /// Merges the runs that are left, and returns everything that was pushed,
+    /// in order. Elements that compare equal stay in the order they were
+    /// pushed in.
+    pub fn finish(mut self) -> Vec<T> {
+        self.collapse(true);
+        while self.runs.len() > 1 {
+            let n = self.runs.force_collapse(&mut Timsort, self.buf.len());
+            self.merge_at(n);
+        }
+        self.buf
+    }
+
+    /// Puts any runs that are known to be over on the stack, and merges them
+    /// as needed. If `finishing`, the run at the end of the buffer is over too.
+    fn collapse(&mut self, finishing: bool) {
+        let len = self.buf.len();
+        while self.pos < len {
+            let pos = self.pos;
+            let c = &mut self.c;
+            let buf = &self.buf;
+            if self.run_end <= pos + 1 {
+                if pos + 1 == len {
+                    self.run_end = len;
+                } else {
+                    self.descending = c(&buf[pos + 1], &buf[pos]) == Ordering::Less;
+                    self.run_end = pos + 2;
+                }
+            }
+            while !self.run_closed && self.run_end < len {
+                if (c(&buf[self.run_end], &buf[self.run_end - 1]) == Ordering::Less) == self.descending {
+                    self.run_end += 1;
+                } else {
+                    self.run_closed = true;
+                }
+            }
+            if self.run_end == len && !finishing {
+                // The run might go on into whatever gets pushed next.
+                return;
+            }
+            let natural = self.run_end - pos;
+            let run_len = if natural >= MIN_RUN {
+                natural
+            } else if finishing {
+                min(MIN_RUN, len - pos)
+            } else if len - pos >= MIN_RUN {
+                MIN_RUN
+            } else {
+                // Too short to go on the stack yet.
+                return;
+            };
+            if self.descending {
+                self.buf[pos..self.run_end].reverse();
+            }
+            let l = &mut self.buf[pos..pos + run_len];
+            let c = &mut self.c;
+            match insort::binary_sort(l, natural, |a, b| Ok::<Ordering, Infallible>(c(a, b)), &mut (), &mut ()) {
+                Ok(()) => (),
+                Err(e) => match e {},
+            }
+            self.runs.push(Run {
+                pos,
+                len: run_len,
+            });
+            self.pos += run_len;
+            self.run_end = self.pos;
+            self.descending = false;
+            self.run_closed = false;
+            while let Some(n) = self.runs.collapse(&mut Timsort, len) {
+                self.merge_at(n);
+            }
+        }
+    }
+
+    /// Merge the run at `n` on the stack with the one above it.
+    fn merge_at(&mut self, n: usize) {
+        let (run1, run2) = self.runs.merge(n);
+        let l = &mut self.buf[run1.pos..run1.pos + run1.len + run2.len];
+        let c = &mut self.c;
+        match merge(l, run1.len, |a, b| Ok::<Ordering, Infallible>(c(a, b)), &mut self.tmp, &mut self.min_gallop, &mut (), &mut ()) {
+            Ok(_) => (),
+            Err(e) => match e {},
+        }
+    }
+}
+
+impl<T, C: FnMut(&T, &T) -> Ordering> Extend<T> for StreamingSorter<T, C> {
+    /// Adds every element from `iter`. Runs are only looked for once they
+    /// have all been added.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.buf.extend(iter);
+        self.collapse(false);
+    }
+}
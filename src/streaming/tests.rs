@@ -0,0 +1,97 @@
+//! A sorter that is fed one element at a time.
+
+use std::vec::Vec;
+use streaming::StreamingSorter;
+use test_util::{check_sorted, pseudo_random, tagged};
+
+/// Lists with a mix of random stretches, ascending runs and descending runs.
+fn lists() -> Vec<Vec<(usize, usize)>> {
+    let mut lists = Vec::new();
+    for &len in &[0, 1, 2, 31, 32, 33, 100, 1000, 5000] {
+        lists.push(tagged(pseudo_random(len, 101, 1)));
+        lists.push((0..len).map(|i| (i / 3, i)).collect());
+        lists.push((0..len).map(|i| ((len - i) / 3, i)).collect());
+        lists.push((0..len).map(|i| if (i / 200) % 2 == 0 { (i % 200, i) } else { (200 - i % 200, i) }).collect());
+    }
+    lists
+}
+
+/// Test pushing elements one at a time.
+#[test]
+fn push() {
+    for list in lists() {
+        let mut sorter = StreamingSorter::new(|a: &(usize, usize), b: &(usize, usize)| a.0.cmp(&b.0));
+        for &x in &list {
+            sorter.push(x);
+        }
+        assert_eq!(sorter.len(), list.len());
+        let sorted = sorter.finish();
+        assert_eq!(sorted.len(), list.len());
+        check_sorted(&sorted);
+    }
+}
+
+/// Test adding elements in batches of different sizes.
+#[test]
+fn extend() {
+    for list in lists() {
+        for &batch in &[1, 7, 64, 1000] {
+            let mut sorter = StreamingSorter::new(|a: &(usize, usize), b: &(usize, usize)| a.0.cmp(&b.0));
+            for chunk in list.chunks(batch) {
+                sorter.extend(chunk.iter().cloned());
+            }
+            let sorted = sorter.finish();
+            assert_eq!(sorted.len(), list.len());
+            check_sorted(&sorted);
+        }
+    }
+}
+
+/// Test that once a short run has ended, pushing more elements doesn't
+/// compare the one that ended it again.
+#[test]
+fn run_end_compared_once() {
+    use std::cell::Cell;
+    let calls = Cell::new(0);
+    let mut sorter = StreamingSorter::new(|a: &usize, b: &usize| {
+        calls.set(calls.get() + 1);
+        a.cmp(b)
+    });
+    sorter.extend(vec![1, 2, 3, 0]);
+    assert_eq!(calls.get(), 3);
+    for x in 0..20 {
+        sorter.push(x);
+    }
+    assert_eq!(calls.get(), 3);
+    assert_eq!(sorter.finish().len(), 24);
+}
+
+/// Test that runs are merged while elements are still coming in, so only a
+/// few are left for `finish`.
+#[test]
+fn merges_early() {
+    let mut sorter = StreamingSorter::new(|a: &usize, b: &usize| a.cmp(b));
+    for x in pseudo_random(100_000, 1009, 0) {
+        sorter.push(x);
+        assert!(sorter.runs.len() <= 20);
+    }
+    assert!(sorter.runs.len() > 1);
+    assert!(sorter.runs.iter().any(|run| run.len > 50_000));
+    let list = sorter.finish();
+    for i in 1..list.len() {
+        assert!(list[i - 1] <= list[i]);
+    }
+}
+
+/// Test that a list that comes in sorted is never merged, however it's split
+/// into batches, and that the run is only put on the stack at the end.
+#[test]
+fn sorted() {
+    let mut sorter = StreamingSorter::new(|a: &usize, b: &usize| a.cmp(b));
+    for i in 0..1000 {
+        sorter.extend(vec![i, i]);
+        assert_eq!(sorter.runs.len(), 0);
+    }
+    let list = sorter.finish();
+    assert!(list.iter().enumerate().all(|(i, &x)| i / 2 == x));
+}
@@ -0,0 +1,34 @@
+//! Lists to sort, and checks on the results, shared by the tests of several
+//! modules.
+
+use std::vec::Vec;
+
+/// `len` keys below `modulus`, in a scattered order with short runs both
+/// ways. A small `modulus` gives lots of ties, and a different `seed` gives a
+/// different list of the same kind.
+pub fn pseudo_random(len: usize, modulus: usize, seed: usize) -> Vec<usize> {
+    (0..len).map(|i| ((i + seed) * 7919) % modulus).collect()
+}
+
+/// Pairs each key with its index, so that stability can be checked.
+pub fn tagged(keys: Vec<usize>) -> Vec<(usize, usize)> {
+    keys.into_iter().enumerate().map(|(i, key)| (key, i)).collect()
+}
+
+/// Checks that `list` is sorted by the key that `f` gives for each element,
+/// and that elements with equal keys are in increasing order of the index it
+/// gives.
+pub fn check_sorted_by<T, K: Ord, I: IntoIterator<Item = T>, F: FnMut(T) -> (K, usize)>(list: I, f: F) {
+    let pairs: Vec<(K, usize)> = list.into_iter().map(f).collect();
+    for w in pairs.windows(2) {
+        assert!(w[0].0 <= w[1].0);
+        if w[0].0 == w[1].0 {
+            assert!(w[0].1 < w[1].1);
+        }
+    }
+}
+
+/// Checks that a list of `(key, index)` pairs is sorted by key, and stable.
+pub fn check_sorted<'a, I: IntoIterator<Item = &'a (usize, usize)>>(list: I) {
+    check_sorted_by(list, |&pair| pair)
+}
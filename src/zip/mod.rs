@@ -0,0 +1,156 @@
+//! Sorting a slice of keys, and putting other slices into the same order, for
+//! data that is kept as a structure of arrays.
+//!
+//! The keys are sorted by `try_sort_in` with the Powersort policy, just as
+//! `sort_by` sorts small elements, and every move it makes is reported through
+//! `Moves` and made again in each of the other slices. Only the keys are ever
+//! compared.
+
+#[cfg(test)]
+mod tests;
+
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::ptr;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use moves::Moves;
+use policy::Powersort;
+use sort::{try_sort_in, RunStack};
+
+/// Sorts a slice of keys, and reorders any number of companion slices the
+/// same way.
+///
+/// ```
+/// let mut timestamps = [30u64, 10, 20];
+/// let mut values = [3.0f32, 1.0, 2.0];
+/// let mut ids = [7u32, 5, 6];
+/// timsort::ZipSort::new(&mut timestamps).with(&mut values).with(&mut ids).sort();
+/// assert_eq!(timestamps, [10, 20, 30]);
+/// assert_eq!(values, [1.0, 2.0, 3.0]);
+/// assert_eq!(ids, [5, 6, 7]);
+/// ```
+///
+/// The sort is stable, and only the keys are compared. The keys get scratch
+/// space for the merges as they need it, and every other slice gets its own,
+/// of half its length, up front.
+pub struct ZipSort<'a, K: 'a> {
+    keys: &'a mut [K],
+    lanes: Lanes<'a>,
+}
+
+impl<'a, K: 'a> ZipSort<'a, K> {
+    /// Starts a sort by `keys`.
+    pub fn new(keys: &'a mut [K]) -> ZipSort<'a, K> {
+        ZipSort {
+            keys,
+            lanes: Lanes(Vec::new()),
+        }
+    }
+
+    /// Adds a slice to be reordered along with the keys.
+    ///
+    /// # Panics
+    ///
+    /// If `slice` isn't the same length as the keys.
+    pub fn with<T: 'a>(mut self, slice: &'a mut [T]) -> ZipSort<'a, K> {
+        assert_eq!(slice.len(), self.keys.len(), "companion slice is the wrong length");
+        self.lanes.0.push(Box::new(Lane::new(slice)));
+        self
+    }
+
+    /// Sorts the keys with a comparator, and reorders the companion slices
+    /// to match.
+    ///
+    /// `c(a, b)` should return std::cmp::Ordering::Greater when `a` is greater than `b`.
+    pub fn sort_by<C: FnMut(&K, &K) -> Ordering>(mut self, mut c: C) {
+        let c = |a: &K, b: &K| Ok::<Ordering, Infallible>(c(a, b));
+        match try_sort_in(self.keys, c, &mut Powersort, &mut RunStack::new(), &mut Vec::new(), &mut (), &mut self.lanes) {
+            Ok(()) => (),
+            Err(e) => match e {},
+        }
+    }
+
+    /// Sorts the keys, and reorders the companion slices to match.
+    pub fn sort(self) where K: Ord {
+        self.sort_by(K::cmp)
+    }
+}
+
+/// One of the other slices, with its own scratch space. The scratch space is
+/// a `Vec` that stays empty, and only its capacity is used.
+struct Lane<'a, T: 'a> {
+    list: &'a mut [T],
+    tmp: Vec<T>,
+}
+
+impl<'a, T: 'a> Lane<'a, T> {
+    fn new(list: &'a mut [T]) -> Lane<'a, T> {
+        // No merge ever copies out more than half of the list. The room is
+        // made now, because running out partway through a merge would leave
+        // this slice with a hole in it that the keys don't have.
+        let tmp = Vec::with_capacity(list.len() / 2);
+        Lane { list, tmp }
+    }
+}
+
+impl<'a, T: 'a> Moves for Lane<'a, T> {
+    fn reverse(&mut self, start: usize, end: usize) {
+        self.list[start..end].reverse();
+    }
+
+    fn rotate(&mut self, start: usize, mid: usize, end: usize) {
+        self.list[start..end].rotate_left(mid - start);
+    }
+
+    unsafe fn stash(&mut self, start: usize, len: usize) {
+        debug_assert!(start + len <= self.list.len() && len <= self.tmp.capacity());
+        ptr::copy_nonoverlapping(self.list.as_ptr().add(start), self.tmp.as_mut_ptr(), len);
+    }
+
+    unsafe fn shift(&mut self, src: usize, dest: usize, len: usize) {
+        debug_assert!(src + len <= self.list.len() && dest + len <= self.list.len());
+        let list = self.list.as_mut_ptr();
+        ptr::copy(list.add(src), list.add(dest), len);
+    }
+
+    unsafe fn unstash(&mut self, src: usize, dest: usize, len: usize) {
+        debug_assert!(src + len <= self.tmp.capacity() && dest + len <= self.list.len());
+        ptr::copy_nonoverlapping(self.tmp.as_ptr().add(src), self.list.as_mut_ptr().add(dest), len);
+    }
+}
+
+/// Every slice but the keys, each of which makes every move the sort makes.
+struct Lanes<'a>(Vec<Box<dyn Moves + 'a>>);
+
+impl<'a> Moves for Lanes<'a> {
+    fn reverse(&mut self, start: usize, end: usize) {
+        for lane in &mut self.0 {
+            lane.reverse(start, end);
+        }
+    }
+
+    fn rotate(&mut self, start: usize, mid: usize, end: usize) {
+        for lane in &mut self.0 {
+            lane.rotate(start, mid, end);
+        }
+    }
+
+    unsafe fn stash(&mut self, start: usize, len: usize) {
+        for lane in &mut self.0 {
+            lane.stash(start, len);
+        }
+    }
+
+    unsafe fn shift(&mut self, src: usize, dest: usize, len: usize) {
+        for lane in &mut self.0 {
+            lane.shift(src, dest, len);
+        }
+    }
+
+    unsafe fn unstash(&mut self, src: usize, dest: usize, len: usize) {
+        for lane in &mut self.0 {
+            lane.unstash(src, dest, len);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Sorting a slice of keys, and putting other slices into the same order.
+
+use std::string::{String, ToString};
+use std::vec::Vec;
+use zip::ZipSort;
+use test_util::{check_sorted_by, pseudo_random};
+
+/// Test that every companion slice ends up in the same order as the keys, and
+/// that equal keys keep their order.
+#[test]
+fn lockstep() {
+    for &len in &[0, 1, 2, 10, 100, 1000] {
+        let mut keys: Vec<u64> = pseudo_random(len, 101, 1).into_iter().map(|x| x as u64).collect();
+        let original = keys.clone();
+        let mut index: Vec<usize> = (0..len).collect();
+        let mut names: Vec<String> = (0..len).map(|i| i.to_string()).collect();
+        let mut halves: Vec<f32> = (0..len).map(|i| i as f32 / 2.0).collect();
+        ZipSort::new(&mut keys).with(&mut index).with(&mut names).with(&mut halves).sort();
+        for i in 0..len {
+            assert_eq!(keys[i], original[index[i]]);
+            assert_eq!(names[i], index[i].to_string());
+            assert_eq!(halves[i], index[i] as f32 / 2.0);
+        }
+        check_sorted_by(0..len, |i| (keys[i], index[i]));
+    }
+}
+
+/// Test sorting with a comparator, and with no companions at all.
+#[test]
+fn sort_by() {
+    let mut keys = [1, 3, 2];
+    let mut values = ['a', 'c', 'b'];
+    ZipSort::new(&mut keys).with(&mut values).sort_by(|a, b| b.cmp(a));
+    assert_eq!(keys, [3, 2, 1]);
+    assert_eq!(values, ['c', 'b', 'a']);
+    ZipSort::new(&mut keys).sort();
+    assert_eq!(keys, [1, 2, 3]);
+}
+
+#[test]
+#[should_panic(expected = "wrong length")]
+fn wrong_length() {
+    let mut keys = [1, 2, 3];
+    let mut values = [1, 2];
+    ZipSort::new(&mut keys).with(&mut values);
+}
+
+/// Test long runs, both ways round, so that runs get reversed and merges
+/// gallop, with every slice kept in step.
+#[test]
+fn runs_and_gallops() {
+    let len = 5000;
+    let mut keys: Vec<usize> = (0..len).map(|i| match (i / 500) % 3 {
+        0 => i % 500,
+        1 => 1000 - i % 500,
+        _ => (i * 7919) % 1009,
+    }).collect();
+    let original = keys.clone();
+    let mut index: Vec<usize> = (0..len).collect();
+    let mut names: Vec<String> = (0..len).map(|i| i.to_string()).collect();
+    ZipSort::new(&mut keys).with(&mut index).with(&mut names).sort();
+    for i in 0..len {
+        assert_eq!(keys[i], original[index[i]]);
+        assert_eq!(names[i], index[i].to_string());
+    }
+    check_sorted_by(0..len, |i| (keys[i], index[i]));
+}
+
+/// Test that a comparator that panics partway through a merge leaves every
+/// slice whole, and still in step.
+#[test]
+fn panic_safe() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    let len = 1000;
+    let keys: Vec<String> = pseudo_random(len, 1009, 0).iter().map(|x| x.to_string()).collect();
+    let mut total = 0;
+    ZipSort::new(&mut keys.clone()).sort_by(|a, b| {
+        total += 1;
+        a.cmp(b)
+    });
+    // Stop during insertion sort, and early and late in the merges.
+    for &stop in &[10, total / 2, total * 3 / 4, total - 1] {
+        let mut keys = keys.clone();
+        let original = keys.clone();
+        let mut index: Vec<usize> = (0..len).collect();
+        let mut calls = 0;
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            ZipSort::new(&mut keys).with(&mut index).sort_by(|a, b| {
+                calls += 1;
+                assert!(calls < stop, "stop");
+                a.cmp(b)
+            });
+        }));
+        assert!(result.is_err());
+        for i in 0..len {
+            assert_eq!(keys[i], original[index[i]]);
+        }
+        index.sort();
+        assert!(index.iter().enumerate().all(|(i, &x)| x == i));
+    }
+}